@@ -7,6 +7,7 @@ use iroh_blobs::api::blobs::{ExportMode, ExportOptions};
 use iroh_blobs::store::fs::FsStore;
 use iroh_blobs::ticket::BlobTicket;
 use iroh_blobs::BlobsProtocol;
+use network::{wait_for_relay, NetworkArgs, RELAY_WAIT};
 use std::path::{absolute, PathBuf};
 use std::str::FromStr;
 
@@ -25,22 +26,26 @@ enum Cmd {
 struct Cli {
     #[command(subcommand)]
     cmd: Cmd,
+    #[command(flatten)]
+    network: NetworkArgs,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    match Endpoint::builder().discovery_n0().bind().await {
+    let cli = Cli::parse();
+    match cli.network.endpoint_builder()?.bind().await {
         Ok(endpoint) => {
             // let memory = MemStore::new();
             let home = dirs_next::home_dir().ok_or_else(|| anyhow!("no home directory"))?;
+            #[allow(clippy::unnecessary_to_owned)]
             let tmp = home.join(".lean".to_string()).join(".data".to_string());
             let temp_store = FsStore::load(tmp).await?;
             // This avoids buffering entire blobs in RAM and avoids an extra copy when the filesystem supports hardlinks/reflinks;
             let blobs_protocol = BlobsProtocol::new(&temp_store, endpoint.clone(), None);
 
-            match Cli::parse().cmd {
+            match cli.cmd {
                 Cmd::Listen { pathname } => {
-                    listen(&blobs_protocol, &endpoint, pathname.as_str()).await?;
+                    listen(&blobs_protocol, &endpoint, &cli.network, pathname.as_str()).await?;
                     // listen to incoming peers
                     route(endpoint, blobs_protocol).await?;
                 }
@@ -59,11 +64,15 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn listen(protocol: &BlobsProtocol, endpoint: &Endpoint, pathname: &str) -> Result<()> {
+async fn listen(protocol: &BlobsProtocol, endpoint: &Endpoint, network: &NetworkArgs, pathname: &str) -> Result<()> {
     let path = PathBuf::from(pathname);
     let abs_path = absolute(path)?;
     let tag = protocol.add_path(abs_path).await?;
-    let node_addr = endpoint.node_id().into();
+    // Put our relay in the ticket so peers that can't reach us directly still can
+    if network.uses_relays() && !wait_for_relay(endpoint).await {
+        println!("No relay answered within {:?} - the ticket only has direct addresses", RELAY_WAIT);
+    }
+    let node_addr = endpoint.node_addr().initialized().await;
     let ticket = BlobTicket::new(node_addr, tag.hash, tag.format);
     println!("TICKET: {}", format!("{}", ticket).blue());
     Ok(())
@@ -0,0 +1,176 @@
+//! The relay and discovery flags every binary in this workspace takes, and
+//! the iroh endpoint they describe.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use iroh::endpoint::{Builder, RelayMode};
+use iroh::{Endpoint, RelayMap, RelayUrl, Watcher};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// How long to wait for a relay before handing out a ticket without one
+pub const RELAY_WAIT: Duration = Duration::from_secs(5);
+
+/// How to find and reach other nodes.
+///
+/// `--local`, `--relay` and `--relay-mode` conflict, so no flag is
+/// silently ignored: `--relay` alone means those relays only.
+#[derive(Args, Debug, Clone, Default)]
+pub struct NetworkArgs {
+    /// Stay on the local network: no relays or n0 discovery, find nodes with mDNS
+    #[arg(long, global = true, conflicts_with_all = ["relays", "relay_mode"])]
+    pub local: bool,
+    /// Relay server to use instead of n0's, e.g. https://relay.example.com (repeatable)
+    #[arg(long = "relay", value_name = "URL", global = true, conflicts_with = "relay_mode")]
+    pub relays: Vec<RelayUrl>,
+    /// Which relays to use (default: n0's, or --relay's if given)
+    #[arg(long, value_enum, global = true)]
+    pub relay_mode: Option<RelayChoice>,
+    /// How to find nodes by node ID, comma-separated (default: n0)
+    #[arg(long, value_enum, value_delimiter = ',', global = true)]
+    pub discovery: Vec<DiscoveryService>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayChoice {
+    /// n0's public relays
+    #[default]
+    Default,
+    /// Only the relays given with --relay or in the config
+    Custom,
+    /// No relays - direct connections only
+    Disabled,
+}
+
+/// A way to look up a node's addresses and relay from its node ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoveryService {
+    /// n0's DNS servers
+    N0,
+    /// The BitTorrent mainline DHT
+    Dht,
+    /// mDNS on the local network
+    Local,
+}
+
+impl RelayChoice {
+    /// The iroh relay mode, with `urls` as the relays for `Custom`.
+    pub fn relay_mode(self, urls: &[RelayUrl]) -> Result<RelayMode> {
+        Ok(match self {
+            RelayChoice::Default => RelayMode::Default,
+            RelayChoice::Disabled => RelayMode::Disabled,
+            RelayChoice::Custom if urls.is_empty() => anyhow::bail!("custom relay mode needs at least one relay"),
+            RelayChoice::Custom => RelayMode::Custom(RelayMap::from_iter(urls.iter().cloned())),
+        })
+    }
+}
+
+impl NetworkArgs {
+    /// The relays asked for: `--relay-mode`, else `--relay`'s, else n0's.
+    pub fn relay_choice(&self) -> RelayChoice {
+        match self.relay_mode {
+            Some(mode) => mode,
+            None if self.relays.is_empty() => RelayChoice::Default,
+            None => RelayChoice::Custom,
+        }
+    }
+
+    /// An endpoint builder with the relays and discovery services asked for.
+    pub fn endpoint_builder(&self) -> Result<Builder> {
+        if self.relay_choice() == RelayChoice::Custom && self.relays.is_empty() {
+            anyhow::bail!("--relay-mode custom needs at least one --relay");
+        }
+        let relay_mode = self.relay_choice().relay_mode(&self.relays)?;
+        let discovery = if self.discovery.is_empty() { &[DiscoveryService::N0][..] } else { &self.discovery };
+        Ok(endpoint_builder(self.local, relay_mode, discovery))
+    }
+
+    pub fn uses_relays(&self) -> bool {
+        !self.local && self.relay_choice() != RelayChoice::Disabled
+    }
+}
+
+/// An endpoint builder with `relay_mode` and `discovery`, or with mDNS alone
+/// and no relays when `local` is set.
+pub fn endpoint_builder(local: bool, relay_mode: RelayMode, discovery: &[DiscoveryService]) -> Builder {
+    if local {
+        return Endpoint::builder().relay_mode(RelayMode::Disabled).discovery_local_network();
+    }
+    let mut builder = Endpoint::builder().relay_mode(relay_mode);
+    for service in discovery {
+        builder = match service {
+            DiscoveryService::N0 => builder.discovery_n0(),
+            DiscoveryService::Dht => builder.discovery_dht(),
+            DiscoveryService::Local => builder.discovery_local_network(),
+        };
+    }
+    builder
+}
+
+/// Waits up to [`RELAY_WAIT`] for the endpoint's home relay, so the address
+/// handed out next includes it. False if no relay answered in time.
+pub async fn wait_for_relay(endpoint: &Endpoint) -> bool {
+    tokio::time::timeout(RELAY_WAIT, endpoint.home_relay().initialized()).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        network: NetworkArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<NetworkArgs, clap::Error> {
+        Cli::try_parse_from(std::iter::once("test").chain(args.iter().copied())).map(|cli| cli.network)
+    }
+
+    #[test]
+    fn relays_alone_mean_custom_relays() {
+        let args = parse(&["--relay", "https://relay.example.com", "--relay", "https://relay2.example.com"]).unwrap();
+        assert_eq!(args.relays.len(), 2);
+        assert_eq!(args.relay_choice(), RelayChoice::Custom);
+        assert!(args.uses_relays());
+    }
+
+    #[test]
+    fn no_flags_mean_n0() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.relay_choice(), RelayChoice::Default);
+        assert!(args.discovery.is_empty());
+        assert!(args.uses_relays());
+    }
+
+    #[test]
+    fn relays_conflict_with_a_relay_mode() {
+        for mode in ["default", "custom", "disabled"] {
+            let err = parse(&["--relay", "https://relay.example.com", "--relay-mode", mode]).unwrap_err();
+            assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict, "--relay-mode {}", mode);
+        }
+    }
+
+    #[test]
+    fn local_conflicts_with_relay_flags() {
+        assert!(parse(&["--local", "--relay", "https://relay.example.com"]).is_err());
+        assert!(parse(&["--local", "--relay-mode", "disabled"]).is_err());
+        assert!(!parse(&["--local", "--discovery", "local"]).unwrap().uses_relays());
+    }
+
+    #[test]
+    fn custom_mode_needs_relays() {
+        let args = parse(&["--relay-mode", "custom"]).unwrap();
+        assert!(args.endpoint_builder().is_err());
+        assert!(RelayChoice::Custom.relay_mode(&[]).is_err());
+    }
+
+    #[test]
+    fn discovery_takes_a_list() {
+        let args = parse(&["--discovery", "n0,dht,local"]).unwrap();
+        assert_eq!(args.discovery, vec![DiscoveryService::N0, DiscoveryService::Dht, DiscoveryService::Local]);
+    }
+}
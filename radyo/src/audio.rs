@@ -1,7 +1,24 @@
 use anyhow::Result;
+use rodio::Source;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, error, trace, warn};
+
+use crate::call::{call_span, CallManager};
+
+// Ringtones play on radyo.toml's audio.output_device, or the default speaker
+fn open_output() -> Result<(rodio::OutputStream, rodio::OutputStreamHandle)> {
+    use rodio::cpal::traits::{DeviceTrait, HostTrait};
+    let Some(name) = CallManager::audio_settings().output_device else {
+        return Ok(rodio::OutputStream::try_default()?);
+    };
+    let device = rodio::cpal::default_host()
+        .output_devices()?
+        .find(|device| device.name().is_ok_and(|n| n == name))
+        .ok_or_else(|| anyhow::anyhow!("no speaker named {}", name))?;
+    Ok(rodio::OutputStream::try_from_device(&device)?)
+}
 
 pub struct AudioManager {
     stop_flag: Arc<AtomicBool>,
@@ -23,6 +40,41 @@ impl AudioManager {
     }
 
     pub fn play_ringtone_async(&self, ringtone_name: &str, call_id: u128) -> Result<tokio::sync::oneshot::Receiver<()>> {
+        self.play_async(ringtone_name, call_id, false)
+    }
+
+    // Hold music reuses the ringtone files but loops until stop() is called
+    pub fn play_hold_music_async(&self, track_name: &str, call_id: u128) -> Result<tokio::sync::oneshot::Receiver<()>> {
+        self.play_async(track_name, call_id, true)
+    }
+
+    // Two short beeps announcing a waiting call
+    pub fn play_beep_async(&self, call_id: u128) -> Result<()> {
+        std::thread::spawn(move || {
+            let _span = call_span(call_id).entered();
+            let beep_result = (|| -> Result<()> {
+                let (_stream, stream_handle) = open_output()?;
+                let sink = rodio::Sink::try_new(&stream_handle)?;
+                for _ in 0..2 {
+                    let tone = rodio::source::SineWave::new(880.0)
+                        .take_duration(std::time::Duration::from_millis(150))
+                        .amplify(0.3);
+                    sink.append(tone);
+                    sink.append(rodio::source::Zero::<f32>::new(1, 44_100)
+                        .take_duration(std::time::Duration::from_millis(100)));
+                }
+                sink.sleep_until_end();
+                Ok(())
+            })();
+            
+            if let Err(e) = beep_result {
+                warn!("Couldn't play the beep: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    fn play_async(&self, ringtone_name: &str, call_id: u128, repeat: bool) -> Result<tokio::sync::oneshot::Receiver<()>> {
         let (audio_ready_tx, audio_ready_rx) = tokio::sync::oneshot::channel();
         let stop_flag = self.stop_flag.clone();
         
@@ -31,33 +83,33 @@ impl AudioManager {
         let file_data = if Path::new(&file_path).exists() {
             std::fs::read(&file_path)?
         } else {
-            println!("Ringtone '{}' not found, using lost_woods.mp3", ringtone_name);
+            warn!("Ringtone '{}' not found, using lost_woods.mp3", ringtone_name);
             std::fs::read("ringtons/lost_woods.mp3")?
         };
 
         let start_time = std::time::Instant::now();
-        println!("⏰ [CALL-{}] Audio thread spawn starting at {:?}", call_id, start_time);
-
         std::thread::spawn(move || {
-            let spawn_delay = start_time.elapsed();
-            println!("🎵 [CALL-{}] Audio thread started (delay: {:?})", call_id, spawn_delay);
+            let _span = call_span(call_id).entered();
+            debug!("Audio thread started after {:?}", start_time.elapsed());
             
             let audio_result = (|| -> Result<()> {
                 let audio_start = std::time::Instant::now();
-                println!("🎵 [CALL-{}] Creating audio output stream...", call_id);
-                let (_stream, stream_handle) = rodio::OutputStream::try_default()?;
+                let (_stream, stream_handle) = open_output()?;
                 
-                println!("🎵 [CALL-{}] Creating audio sink...", call_id);
                 let sink = rodio::Sink::try_new(&stream_handle)?;
                 
                 let cursor = std::io::Cursor::new(file_data);
                 let source = rodio::Decoder::new(cursor)?;
                 
-                sink.append(source);
+                if repeat {
+                    sink.append(source.repeat_infinite());
+                } else {
+                    sink.append(source);
+                }
                 sink.set_volume(0.5);
                 
                 let setup_time = audio_start.elapsed();
-                println!("🎵 [CALL-{}] Audio ready! Setup time: {:?} - RINGTONE SHOULD BE PLAYING NOW", call_id, setup_time);
+                debug!("Audio playing after {:?} of setup", setup_time);
                 
                 // Signal that audio is ready
                 let _ = audio_ready_tx.send(());
@@ -66,20 +118,20 @@ impl AudioManager {
                 let mut check_count = 0;
                 loop {
                     if sink.empty() {
-                        println!("📞 [CALL-{}] Ringtone finished naturally (after {} checks)", call_id, check_count);
+                        debug!("Audio finished after {} checks", check_count);
                         break;
                     }
                     
                     // Check if we should stop
                     if stop_flag.load(Ordering::Relaxed) {
-                        println!("📞 [CALL-{}] Ringtone stopped by hangup signal (after {} checks)", call_id, check_count);
+                        debug!("Audio stopped after {} checks", check_count);
                         sink.stop();
                         break;
                     }
                     
                     check_count += 1;
                     if check_count % 10 == 0 {
-                        println!("🔄 [CALL-{}] Audio thread alive - check #{}", call_id, check_count);
+                        trace!("Audio thread alive - check #{}", check_count);
                     }
                     
                     std::thread::sleep(std::time::Duration::from_millis(100));
@@ -88,9 +140,9 @@ impl AudioManager {
             })();
             
             if let Err(e) = audio_result {
-                println!("❌ [CALL-{}] Audio thread error: {}", call_id, e);
+                error!("Audio thread error: {}", e);
             }
-            println!("🎵 [CALL-{}] Audio thread completed", call_id);
+            debug!("Audio thread completed");
         });
 
         Ok(audio_ready_rx)
@@ -1,19 +1,88 @@
 use anyhow::Result;
-use iroh::endpoint::Connection;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::OnceLock;
-#[allow(unused_imports)]
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use chrono::Local;
+use iroh::endpoint::{Connection, SendStream};
+use iroh::{Endpoint, NodeAddr, NodeId};
+use iroh_base::ticket::NodeTicket;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
 use crate::audio::AudioManager;
+use crate::chat::{ChatLink, ChatLog, ChatMessage};
+use crate::connect::connect;
+use crate::config::{AudioSettings, RetrySettings, Timeouts};
+use crate::contacts::ContactBook;
+use crate::files::{download_file, format_size, share_file, FileOffer};
+use crate::dnd::DndRules;
+use crate::forwarding::ForwardingRules;
+use crate::presence::{answer_presence, local_presence};
+use crate::recording::{CallRecorder, RecordingMode};
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+use crate::stats::{spawn_stats_logger, CallStats};
+use crate::voice::{VoiceControls, VoiceLink};
+
+// How long to wait for HANGUP_ACK after hanging up
+pub const HANGUP_ACK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// How long to wait for the dialer to read a final BUSY or REDIRECT
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
 
 // Global storage for caller's ringtone preference
 static CALLER_RINGTONE: OnceLock<String> = OnceLock::new();
 
+// Track played while the other side has put us on hold
+static HOLD_MUSIC: OnceLock<String> = OnceLock::new();
+
+// Global channel delivering the local user's commands to the active call
+// Each command is tagged with the call it's for
+static CALL_COMMANDS: OnceLock<tokio::sync::broadcast::Sender<(u128, CallCommand)>> = OnceLock::new();
+
 // Global hangup signal - can be triggered by either side
 static HANGUP_SIGNAL: OnceLock<tokio::sync::broadcast::Sender<()>> = OnceLock::new();
 
-// Global call state - ensure only one call at a time
-static CALL_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+// Every call in progress on this node, and which one the user's keys control
+static CALLS: Mutex<CallRegistry> = Mutex::new(CallRegistry { calls: Vec::new(), focused: None });
+
+// Wakes anyone waiting for the last call to end
+static CALLS_CHANGED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+// Whether a second caller waits instead of getting BUSY
+static CALL_WAITING: AtomicBool = AtomicBool::new(false);
+
+// Whether live connection stats should be printed during calls
+static STATS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Events for library users, tagged with the call they happened on
+static CALL_EVENTS: OnceLock<tokio::sync::broadcast::Sender<(u128, CallEvent)>> = OnceLock::new();
+
+// Whether keypad digits are also played to the other side as tones
+static DTMF_TONES: AtomicBool = AtomicBool::new(false);
+
+// Whether the other side may record our calls
+static RECORDING_ALLOWED: AtomicBool = AtomicBool::new(true);
+
+// Where incoming calls get forwarded, if anywhere
+static FORWARDING: OnceLock<ForwardingRules> = OnceLock::new();
+
+// Timeouts from radyo.toml and the command line
+static TIMEOUTS: OnceLock<Timeouts> = OnceLock::new();
+
+// How often and how patiently to retry nodes that can't be reached
+static RETRY: OnceLock<RetrySettings> = OnceLock::new();
+
+// Volume and devices new calls start with
+static AUDIO_SETTINGS: OnceLock<AudioSettings> = OnceLock::new();
+
+// Whether incoming calls are turned away instead of ringing
+static DO_NOT_DISTURB: AtomicBool = AtomicBool::new(false);
+
+// Quiet hours, and who may ring through them
+static DND_RULES: OnceLock<DndRules> = OnceLock::new();
+
+// Finished calls, most recent last
+static CALL_HISTORY: Mutex<Vec<CallRecord>> = Mutex::new(Vec::new());
 
 pub struct CallManager;
 
@@ -26,100 +95,611 @@ impl CallManager {
         CALLER_RINGTONE.get().cloned().unwrap_or_else(|| "lost_woods".to_string())
     }
 
+    pub fn set_hold_music(track: String) -> Result<()> {
+        HOLD_MUSIC.set(track).map_err(|_| anyhow::anyhow!("Failed to set hold music"))
+    }
+
+    pub fn get_hold_music() -> String {
+        HOLD_MUSIC.get().cloned().unwrap_or_else(|| "taken_it_slow".to_string())
+    }
+
     pub fn is_call_in_progress() -> bool {
-        CALL_IN_PROGRESS.load(Ordering::Relaxed)
+        !CALLS.lock().unwrap().calls.is_empty()
+    }
+
+    pub fn set_call_waiting(enabled: bool) {
+        CALL_WAITING.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn call_waiting_enabled() -> bool {
+        CALL_WAITING.load(Ordering::Relaxed)
+    }
+
+    pub fn set_dtmf_tones(enabled: bool) {
+        DTMF_TONES.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn dtmf_tones_enabled() -> bool {
+        DTMF_TONES.load(Ordering::Relaxed)
+    }
+
+    pub fn set_recording_allowed(allowed: bool) {
+        RECORDING_ALLOWED.store(allowed, Ordering::Relaxed);
+    }
+
+    pub fn recording_allowed() -> bool {
+        RECORDING_ALLOWED.load(Ordering::Relaxed)
+    }
+
+    // Claim a line for a new call - None means the phone is busy
+    pub fn try_acquire_call(call_id: u128) -> Option<CallSlot> {
+        let mut registry = CALLS.lock().unwrap();
+        // A call that has ended but not finished cleaning up no longer holds the line
+        let live_calls = registry.calls.iter().filter(|(_, phase)| *phase != CallPhase::Ended).count();
+        let slot = match live_calls {
+            0 => CallSlot::Primary,
+            1 if Self::call_waiting_enabled() => CallSlot::Waiting,
+            _ => return None,
+        };
+        let phase = match slot {
+            CallSlot::Primary => CallPhase::Ringing,
+            CallSlot::Waiting => CallPhase::Waiting,
+        };
+        registry.calls.push((call_id, phase));
+        if registry.focused.is_none() {
+            registry.focused = Some(call_id);
+        }
+        Some(slot)
+    }
+
+    // Calls we place always get a line, and the user's keys move to them
+    pub fn register_outgoing_call(call_id: u128) {
+        let mut registry = CALLS.lock().unwrap();
+        registry.calls.push((call_id, CallPhase::Ringing));
+        registry.focused = Some(call_id);
+    }
+
+    // Free the call's line; if it had focus, hand focus to the next call
+    pub fn release_call(call_id: u128) {
+        let next = {
+            let mut registry = CALLS.lock().unwrap();
+            registry.calls.retain(|(id, _)| *id != call_id);
+            CALLS_CHANGED.notify_waiters();
+            if registry.focused != Some(call_id) {
+                return;
+            }
+            registry.focused = registry.calls.first().map(|(id, _)| *id);
+            registry.focused
+        };
+        if let Some(next) = next {
+            send_command(CallCommand::Focus(next));
+        }
+    }
+
+    /// Waits until no calls are in progress, e.g. a call we were transferred into.
+    pub async fn wait_until_idle() {
+        loop {
+            let changed = CALLS_CHANGED.notified();
+            if !Self::is_call_in_progress() {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// All calls in progress with their current phase, oldest first.
+    pub fn active_calls() -> Vec<(u128, CallPhase)> {
+        CALLS.lock().unwrap().calls.clone()
+    }
+
+    /// The oldest call that is ringing or waiting for an answer.
+    pub fn ringing_call() -> Option<u128> {
+        Self::active_calls()
+            .into_iter()
+            .find(|(_, phase)| matches!(phase, CallPhase::Ringing | CallPhase::Waiting))
+            .map(|(call_id, _)| call_id)
+    }
+
+    pub fn call_phase(call_id: u128) -> Option<CallPhase> {
+        Self::active_calls().into_iter().find(|(id, _)| *id == call_id).map(|(_, phase)| phase)
+    }
+
+    pub fn focused_call() -> Option<u128> {
+        CALLS.lock().unwrap().focused
+    }
+
+    pub fn is_focused(call_id: u128) -> bool {
+        Self::focused_call() == Some(call_id)
+    }
+
+    pub fn set_focus(call_id: u128) {
+        CALLS.lock().unwrap().focused = Some(call_id);
+    }
+
+    pub fn other_call(call_id: u128) -> Option<u128> {
+        CALLS.lock().unwrap().calls.iter().map(|(id, _)| *id).find(|id| *id != call_id)
+    }
+
+    pub fn set_call_phase(call_id: u128, phase: CallPhase) {
+        let mut registry = CALLS.lock().unwrap();
+        if let Some(entry) = registry.calls.iter_mut().find(|(id, _)| *id == call_id) {
+            entry.1 = phase;
+        }
+    }
+
+    pub fn set_stats_enabled(enabled: bool) {
+        STATS_ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn stats_enabled() -> bool {
+        STATS_ENABLED.load(Ordering::Relaxed)
+    }
+
+    pub fn set_forwarding(rules: ForwardingRules) -> Result<()> {
+        FORWARDING.set(rules).map_err(|_| anyhow::anyhow!("Failed to set forwarding rules"))
+    }
+
+    pub fn forwarding() -> ForwardingRules {
+        FORWARDING.get().cloned().unwrap_or_default()
+    }
+
+    pub fn set_timeouts(timeouts: Timeouts) -> Result<()> {
+        TIMEOUTS.set(timeouts).map_err(|_| anyhow::anyhow!("Failed to set timeouts"))
+    }
+
+    pub fn timeouts() -> Timeouts {
+        TIMEOUTS.get().copied().unwrap_or_default()
+    }
+
+    pub fn set_retry(retry: RetrySettings) -> Result<()> {
+        RETRY.set(retry).map_err(|_| anyhow::anyhow!("Failed to set retry settings"))
+    }
+
+    pub fn retry() -> RetrySettings {
+        RETRY.get().copied().unwrap_or_default()
+    }
+
+    pub fn set_audio_settings(settings: AudioSettings) -> Result<()> {
+        AUDIO_SETTINGS.set(settings).map_err(|_| anyhow::anyhow!("Failed to set audio settings"))
+    }
+
+    pub fn audio_settings() -> AudioSettings {
+        AUDIO_SETTINGS.get().cloned().unwrap_or_default()
+    }
+
+    pub fn set_do_not_disturb(enabled: bool) {
+        DO_NOT_DISTURB.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn do_not_disturb() -> bool {
+        DO_NOT_DISTURB.load(Ordering::Relaxed)
+    }
+
+    /// Flips the do-not-disturb toggle, returning whether it is now on.
+    pub fn toggle_do_not_disturb() -> bool {
+        !DO_NOT_DISTURB.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    pub fn set_dnd_rules(rules: DndRules) -> Result<()> {
+        DND_RULES.set(rules).map_err(|_| anyhow::anyhow!("Failed to set do-not-disturb rules"))
+    }
+
+    pub fn dnd_rules() -> DndRules {
+        DND_RULES.get().cloned().unwrap_or_default()
+    }
+
+    /// Whether calls are being turned away right now - toggled on, or in quiet hours.
+    pub fn dnd_active() -> bool {
+        Self::do_not_disturb() || Self::dnd_rules().in_quiet_hours(Local::now().time())
     }
 
-    pub fn try_acquire_call() -> bool {
-        CALL_IN_PROGRESS.compare_exchange(
-            false, 
-            true, 
-            Ordering::Acquire,
-            Ordering::Relaxed
-        ).is_ok()
+    pub fn record_call(record: CallRecord) {
+        CALL_HISTORY.lock().unwrap().push(record);
+    }
+
+    pub fn call_history() -> Vec<CallRecord> {
+        CALL_HISTORY.lock().unwrap().clone()
+    }
+}
+
+struct CallRegistry {
+    calls: Vec<(u128, CallPhase)>,
+    focused: Option<u128>,
+}
+
+/// How a new call got onto the phone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallSlot {
+    /// The only call - it rings normally
+    Primary,
+    /// Another call is in progress - this one beeps and waits
+    Waiting,
+}
+
+/// How a call ended, as seen from this side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallOutcome {
+    /// We hung up
+    HungUp,
+    /// The other side hung up or closed the call
+    RemoteHungUp,
+    Busy,
+    /// The other side's do-not-disturb turned us away
+    DoNotDisturb,
+    /// An outgoing call nobody answered
+    NoAnswer,
+    /// The other side forwarded us - dial this node instead
+    Redirected(NodeTicket),
+    /// We forwarded the caller to this node
+    Forwarded(NodeTicket),
+    /// The other side transferred us - a new call to this node carries on
+    Transferred(NodeTicket),
+    ConnectionLost,
+}
+
+/// Summary of a finished call, kept in the call history.
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    pub call_id: u128,
+    /// None if the connection never told us who the other side was
+    pub remote: Option<NodeId>,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    pub stats: CallStats,
+    pub chat: Vec<ChatMessage>,
+}
+
+impl CallRecord {
+    // Take the final stats sample for a call that started at `started`
+    pub fn finish(call_id: u128, endpoint: &Endpoint, conn: &Connection, started_at: SystemTime, started: Instant, chat: &ChatLog) -> Self {
+        Self {
+            call_id,
+            remote: conn.remote_node_id().ok(),
+            started_at,
+            duration: started.elapsed(),
+            stats: CallStats::sample(endpoint, conn),
+            chat: chat.messages(),
+        }
     }
+}
 
-    pub fn release_call() {
-        CALL_IN_PROGRESS.store(false, Ordering::Release);
+/// Where a call is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallPhase {
+    Ringing,
+    /// Ringing while we're busy on another call
+    Waiting,
+    Active,
+    OnHold { by_us: bool },
+    Ended,
+}
+
+impl fmt::Display for CallPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallPhase::Ringing => f.write_str("Ringing"),
+            CallPhase::Waiting => f.write_str("Waiting (call waiting)"),
+            CallPhase::Active => f.write_str("Active"),
+            CallPhase::OnHold { by_us: true } => f.write_str("OnHold (you put the call on hold)"),
+            CallPhase::OnHold { by_us: false } => f.write_str("OnHold (the other side put you on hold)"),
+            CallPhase::Ended => f.write_str("Ended"),
+        }
     }
 }
 
 pub struct CallState {
     pub call_id: u128,
+    pub phase: CallPhase,
+}
+
+impl Default for CallState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Counts up so every call, conference and channel in this process gets its own ID
+static NEXT_CALL_ID: AtomicU64 = AtomicU64::new(1);
+
+// Short ID for a call, conference or channel, shown in its log lines
+pub fn new_call_id() -> u128 {
+    NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed).into()
 }
 
 impl CallState {
     pub fn new() -> Self {
-        let call_id = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() % 10000; // Short ID for this call
-        
-        Self { call_id }
+        Self { call_id: new_call_id(), phase: CallPhase::Ringing }
+    }
+
+    pub fn set_phase(&mut self, phase: CallPhase) {
+        debug!("State: {} → {}", self.phase, phase);
+        self.phase = phase;
+        CallManager::set_call_phase(self.call_id, phase);
+        emit_event(self.call_id, CallEvent::PhaseChanged(phase));
+    }
+}
+
+/// Things that happen on a call, for programs built on radyo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallEvent {
+    /// A call started ringing, on either side
+    Started { remote: NodeId, direction: CallDirection },
+    PhaseChanged(CallPhase),
+    /// The other side pressed a keypad digit
+    Dtmf(char),
+    /// Connection quality, every [`crate::stats::LIVE_STATS_INTERVAL`]
+    Stats(CallStats),
+    /// Peak microphone and speaker levels in percent, several times a second
+    Levels { mic: u8, speaker: u8 },
+    /// Our microphone was muted (true) or unmuted
+    Muted(bool),
+}
+
+/// Actions the local user can take on the current call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallCommand {
+    Answer,
+    /// Turn a ringing call away with BUSY
+    Decline,
+    Hold,
+    Resume,
+    Hangup,
+    Mute,
+    Unmute,
+    ToggleMute,
+    VolumeUp,
+    VolumeDown,
+    /// Playback volume in percent
+    SetVolume(u8),
+    /// Put the current call on hold and take the other one
+    Switch,
+    /// Sent to the call being switched to
+    SwitchTo(u128),
+    /// Sent to the call that gets focus after the focused call ended
+    Focus(u128),
+    /// Blind transfer: the other side dials this node and drops us
+    Transfer(NodeTicket),
+    /// Attended transfer: hold this call and dial the target to talk first
+    ConsultTransfer(NodeTicket),
+    /// Hand the held call over to the target we consulted
+    CompleteTransfer,
+    /// Send a text message on the call's chat
+    Chat(String),
+    /// Offer the other side a file to download
+    SendFile(PathBuf),
+    /// Download the file the other side offered last
+    AcceptFile,
+    /// Ask to start recording, or stop the recording
+    ToggleRecording(RecordingMode),
+    /// Press keypad digits, e.g. to drive an IVR menu
+    Dtmf(String),
+}
+
+impl CallCommand {
+    // Most commands go to the focused call; these name the call they're for
+    fn target(&self) -> Option<u128> {
+        match self {
+            CallCommand::SwitchTo(call_id) | CallCommand::Focus(call_id) => Some(*call_id),
+            _ => None,
+        }
     }
 }
 
+// How much VolumeUp and VolumeDown change the volume, in percent
+const VOLUME_STEP: u8 = 10;
+
+impl fmt::Display for CallCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallCommand::Answer => f.write_str("answer"),
+            CallCommand::Decline => f.write_str("decline"),
+            CallCommand::Hold => f.write_str("hold"),
+            CallCommand::Resume => f.write_str("resume"),
+            CallCommand::Hangup => f.write_str("hang up"),
+            CallCommand::Mute => f.write_str("mute"),
+            CallCommand::Unmute => f.write_str("unmute"),
+            CallCommand::ToggleMute => f.write_str("toggle mute"),
+            CallCommand::VolumeUp => f.write_str("turn the volume up"),
+            CallCommand::VolumeDown => f.write_str("turn the volume down"),
+            CallCommand::SetVolume(percent) => write!(f, "set the volume to {}%", percent),
+            CallCommand::Switch => f.write_str("switch calls"),
+            CallCommand::SwitchTo(call_id) => write!(f, "switch to CALL-{}", call_id),
+            CallCommand::Focus(call_id) => write!(f, "focus CALL-{}", call_id),
+            CallCommand::Transfer(target) => write!(f, "transfer the call to {}", short_name(target)),
+            CallCommand::ConsultTransfer(target) => write!(f, "start a transfer to {}", short_name(target)),
+            CallCommand::CompleteTransfer => f.write_str("complete the transfer"),
+            CallCommand::Chat(_) => f.write_str("send a chat message"),
+            CallCommand::SendFile(path) => write!(f, "send {}", path.display()),
+            CallCommand::AcceptFile => f.write_str("download a file"),
+            CallCommand::ToggleRecording(_) => f.write_str("record the call"),
+            CallCommand::Dtmf(digits) => write!(f, "press {}", digits),
+        }
+    }
+}
+
+fn short_name(ticket: &NodeTicket) -> String {
+    ticket.node_addr().node_id.fmt_short()
+}
+
 // Initialize the hangup signal system
 pub fn init_hangup_system() -> tokio::sync::broadcast::Receiver<()> {
-    let (sender, receiver) = tokio::sync::broadcast::channel(1);
-    
-    // Store the sender globally so hangup() can access it (only if not already set)
-    let _ = HANGUP_SIGNAL.set(sender); // Ignore error if already set
-    
-    receiver
+    // Store the sender globally so hang_up_all() can access it (only if not already set)
+    HANGUP_SIGNAL
+        .get_or_init(|| tokio::sync::broadcast::channel(1).0)
+        .subscribe()
+}
+
+// Hang up the focused call
+pub fn hangup() -> bool {
+    send_command(CallCommand::Hangup)
 }
 
-// Hangup function that can be called by either side
-pub async fn hangup() -> Result<()> {
+// Hang up every call at once, e.g. when shutting down
+pub async fn hang_up_all() -> Result<()> {
     if let Some(sender) = HANGUP_SIGNAL.get() {
-        println!("📞 Initiating hangup...");
+        debug!("Hanging up every call...");
         let _ = sender.send(()); // Notify all listeners
-        println!("✅ Hangup signal sent");
+        debug!("Hangup signal sent");
     }
     Ok(())
 }
 
-pub async fn incoming_call_handler(conn: Connection) {
+// Mute the microphone on the active call
+pub fn mute() -> bool {
+    send_command(CallCommand::Mute)
+}
+
+pub fn unmute() -> bool {
+    send_command(CallCommand::Unmute)
+}
+
+// Set the active call's playback volume in percent (0-200)
+pub fn set_volume(percent: u8) -> bool {
+    send_command(CallCommand::SetVolume(percent))
+}
+
+fn command_sender() -> &'static tokio::sync::broadcast::Sender<(u128, CallCommand)> {
+    CALL_COMMANDS.get_or_init(|| tokio::sync::broadcast::channel(16).0)
+}
+
+// Deliver a command to the focused call - returns false if no call is listening.
+// The target is fixed here so focus changes can't redirect a command in flight.
+pub fn send_command(command: CallCommand) -> bool {
+    let Some(target) = command.target().or_else(CallManager::focused_call) else {
+        return false;
+    };
+    command_sender().send((target, command)).is_ok()
+}
+
+// Deliver a command to one call, whether or not it has focus
+pub fn send_command_to(call_id: u128, command: CallCommand) -> bool {
+    command_sender().send((call_id, command)).is_ok()
+}
+
+// Answer `call_id` - a waiting call is taken by putting the current one on hold
+pub fn answer_call(call_id: u128) -> bool {
+    if CallManager::call_phase(call_id) == Some(CallPhase::Waiting) {
+        send_command(CallCommand::Switch)
+    } else {
+        send_command_to(call_id, CallCommand::Answer)
+    }
+}
+
+pub fn subscribe_commands() -> tokio::sync::broadcast::Receiver<(u128, CallCommand)> {
+    command_sender().subscribe()
+}
+
+fn event_sender() -> &'static tokio::sync::broadcast::Sender<(u128, CallEvent)> {
+    CALL_EVENTS.get_or_init(|| tokio::sync::broadcast::channel(256).0)
+}
+
+pub(crate) fn emit_event(call_id: u128, event: CallEvent) {
+    let _ = event_sender().send((call_id, event));
+}
+
+/// Events from every call, tagged with the call ID.
+pub fn subscribe_events() -> tokio::sync::broadcast::Receiver<(u128, CallEvent)> {
+    event_sender().subscribe()
+}
+
+// The span a call's log lines are recorded in, so they carry its ID
+pub(crate) fn call_span(call_id: u128) -> Span {
+    info_span!("call", call_id)
+}
+
+pub async fn incoming_call_handler(endpoint: Endpoint, conn: Connection) {
     let call_state = CallState::new();
-    
-    println!("📞 [CALL-{}] New incoming call session started", call_state.call_id);
-    if let Err(e) = handle_incoming_call(conn, call_state.call_id).await {
-        eprintln!("❌ [CALL-{}] Call handling error: {}", call_state.call_id, e);
+    let span = call_span(call_state.call_id);
+    async move {
+        debug!("New incoming call session started");
+        if let Err(e) = handle_incoming_call(endpoint, conn, call_state).await {
+            error!("Call handling error: {}", e);
+        }
+        debug!("Call session ended - ready for next call");
     }
-    println!("📞 [CALL-{}] Call session ended - ready for next call", call_state.call_id);
+    .instrument(span)
+    .await
 }
 
-async fn handle_incoming_call(conn: Connection, call_id: u128) -> Result<()> {
-    println!("📞 [CALL-{}] Incoming call detected!", call_id);
+async fn handle_incoming_call(endpoint: Endpoint, conn: Connection, call_state: CallState) -> Result<()> {
+    let call_id = call_state.call_id;
     
     // Accept the bidirectional stream
-    let (mut send, mut recv) = conn.accept_bi().await?;
-    
-    // Read the incoming call signal
-    let mut buffer = [0u8; 13]; // "INCOMING_CALL" length
-    recv.read_exact(&mut buffer).await?;
+    let (mut send, recv) = conn.accept_bi().await?;
+    let mut signals = SignalReader::new(recv);
     
-    if &buffer == b"INCOMING_CALL" {
-        // Try to acquire call lock - only one call at a time
-        if !CallManager::try_acquire_call() {
+    // Read the incoming call signal - or a presence probe, which never rings
+    let opening = signals.next().await?;
+    if opening == Some(Signal::Presence) {
+        return answer_presence(send, local_presence(conn.remote_node_id()?)).await;
+    }
+    if opening == Some(Signal::IncomingCall) {
+        info!("Incoming call detected!");
+        let forwarding = CallManager::forwarding();
+        if let Some(target) = &forwarding.always {
+            return forward_call(&mut send, target).await;
+        }
+        // Turn the caller away before anything starts ringing, unless they're a favourite
+        if CallManager::dnd_active() {
+            let rules = CallManager::dnd_rules();
+            let caller = conn.remote_node_id()?;
+            let name = ContactBook::load_or_default().display_name(caller);
+            if rules.lets_through(caller) {
+                info!("Do not disturb is on, but {} is a favourite - ringing", name);
+            } else if let Some(voicemail) = &rules.voicemail {
+                info!("Do not disturb is on - sending {} to voicemail", name);
+                return forward_call(&mut send, voicemail).await;
+            } else {
+                info!("Do not disturb is on - turning {} away", name);
+                send_signal(&mut send, &Signal::DoNotDisturb).await?;
+                close_and_wait(&mut send).await;
+                return Ok(());
+            }
+        }
+        
+        // Try to claim a line - a second call may wait if call waiting is on
+        let Some(slot) = CallManager::try_acquire_call(call_id) else {
+            if let Some(target) = &forwarding.on_busy {
+                return forward_call(&mut send, target).await;
+            }
             // Another call is in progress - send busy signal and close
-            println!("📞 [CALL-{}] Phone is busy - rejecting call", call_id);
-            send.write_all(b"BUSY").await?;
-            send.finish()?; // Close the send stream
+            info!("Phone is busy - rejecting call");
+            send_signal(&mut send, &Signal::Busy).await?;
+            close_and_wait(&mut send).await;
             return Ok(());
-        }
+        };
         
-        println!("📞 [CALL-{}] Confirmed incoming call - phone is now busy", call_id);
+        debug!("Confirmed incoming call - phone is now busy");
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let stats_logger = spawn_stats_logger(endpoint.clone(), conn.clone(), call_id);
         
-        // Get the caller's preferred ringtone
-        let ringtone_name = CallManager::get_ringtone();
+        // Ring until the call is answered, then run it until either side hangs up
+        let session = CallSession::new(call_state, CallDirection::Incoming, endpoint.clone(), conn.clone(), send, signals);
+        let chat = session.chat_log();
+        let result = match slot {
+            CallSlot::Primary => match play_caller_ringtone(&ringtone_for(&conn), call_id).await {
+                Ok(ringtone) => session.with_ringtone(ringtone).run().await,
+                Err(e) => Err(e),
+            },
+            CallSlot::Waiting => {
+                let mut session = session;
+                session.state.phase = CallPhase::Waiting;
+                announce_waiting_call(call_id);
+                session.run().await
+            }
+        };
         
-        // Play the caller's ringtone and listen for hangup signal with acknowledgment
-        let result = play_caller_ringtone_with_hangup_ack(&ringtone_name, recv, send, call_id).await;
+        stats_logger.abort();
+        let record = CallRecord::finish(call_id, &endpoint, &conn, started_at, started, &chat);
+        if CallManager::stats_enabled() {
+            info!("Call summary: {} over {:?}", record.stats, record.duration);
+        }
+        CallManager::record_call(record);
         
-        // Always free the call lock when done
-        CallManager::release_call();
-        println!("📞 [CALL-{}] Phone is now available for new calls", call_id);
+        // Always free the line when done
+        CallManager::release_call(call_id);
+        debug!("Line is now free");
         
         result?;
     }
@@ -127,125 +707,692 @@ async fn handle_incoming_call(conn: Connection, call_id: u128) -> Result<()> {
     Ok(())
 }
 
-// Function that listens for HANGUP message and sends acknowledgment
-async fn play_caller_ringtone_with_hangup_ack(
-    ringtone_name: &str, 
-    mut recv: iroh::endpoint::RecvStream, 
-    mut send: iroh::endpoint::SendStream, 
-    call_id: u128
-) -> Result<()> {
-    println!("🎵 [CALL-{}] Playing caller's ringtone: {}", call_id, ringtone_name);
-    
-    // Create per-call hangup channel - NO GLOBAL STATE!
-    let (call_hangup_tx, mut call_hangup_rx) = tokio::sync::broadcast::channel::<()>(1);
-    println!("📡 [CALL-{}] Created independent hangup channel for this call", call_id);
+// Tell the dialer to call `target` instead, without ringing here
+pub(crate) async fn forward_call(send: &mut SendStream, target: &NodeTicket) -> Result<()> {
+    info!("Forwarding call to {}", short_name(target));
+    send_signal(send, &Signal::Redirect(target.clone())).await?;
+    close_and_wait(send).await;
+    Ok(())
+}
+
+// Finish the stream and give the dialer a moment to read what's left,
+// so dropping the connection doesn't cut off the last signal
+pub(crate) async fn close_and_wait(send: &mut SendStream) {
+    if send.finish().is_ok() {
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, send.stopped()).await;
+    }
+}
+
+// Beep instead of ringing so the current call isn't drowned out
+fn announce_waiting_call(call_id: u128) {
+    info!("Call waiting - press 's' to hold your current call and take this one");
+    info!("Or hang up your current call with 'q' and this one will ring");
+    if let Err(e) = AudioManager::new().play_beep_async(call_id) {
+        warn!("Couldn't play call waiting beep: {}", e);
+    }
+}
+
+// The caller's own ringtone, or their group's, so you can tell who's calling by ear
+fn ringtone_for(conn: &Connection) -> String {
+    conn.remote_node_id()
+        .ok()
+        .and_then(|caller| ContactBook::load().ok()?.ringtone_for(caller))
+        .unwrap_or_else(CallManager::get_ringtone)
+}
+
+// Start the ringtone and wait until the audio thread is actually playing
+async fn play_caller_ringtone(ringtone_name: &str, call_id: u128) -> Result<AudioManager> {
+    debug!("Playing caller's ringtone: {}", ringtone_name);
     
     // Create audio manager and start playing
     let audio_manager = AudioManager::new();
     let audio_ready_rx = audio_manager.play_ringtone_async(ringtone_name, call_id)?;
     
-    println!("🔊 [CALL-{}] Ringtone playing on caller's device...", call_id);
-    println!("💡 [CALL-{}] Press Ctrl+C or call hangup() to stop", call_id);
+    debug!("Ringtone playing on caller's device...");
+    info!("Type 'a' and Enter to answer, or Ctrl+C to stop");
     
-    println!("⚡ [CALL-{}] Audio thread spawned, waiting for audio to be ready...", call_id);
+    debug!("Audio thread spawned, waiting for audio to be ready...");
     
-    // Wait for audio to be ready before starting hangup monitoring
-    match tokio::time::timeout(tokio::time::Duration::from_secs(5), audio_ready_rx).await {
+    // Wait for audio to be ready before starting call monitoring
+    match tokio::time::timeout(CallManager::timeouts().audio_ready(), audio_ready_rx).await {
         Ok(Ok(())) => {
-            println!("✅ [CALL-{}] Audio confirmed ready - starting call monitoring", call_id);
+            debug!("Audio confirmed ready - starting call monitoring");
         }
         Ok(Err(_)) => {
-            println!("⚠️ [CALL-{}] Audio ready channel closed - continuing anyway", call_id);
+            warn!("Audio ready channel closed - continuing anyway");
         }
         Err(_) => {
-            println!("⚠️ [CALL-{}] Audio ready timeout - continuing anyway", call_id);
+            warn!("Audio ready timeout - continuing anyway");
         }
     }
     
-    // Create a dummy audio task for the select! to work with
-    let audio_task = tokio::task::spawn(async {
-        // Just wait indefinitely - the real audio runs in the dedicated thread above
-        tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
-        Ok::<(), anyhow::Error>(())
-    });
+    Ok(audio_manager)
+}
+
+/// A call we placed that is now ringing the other side.
+pub struct OutgoingCall {
+    endpoint: Endpoint,
+    conn: Connection,
+    session: CallSession,
+}
+
+/// Connects to `node_addr` and rings it.
+///
+/// The call only takes a line once the other side has been reached, so a
+/// failed dial leaves the current calls untouched.
+pub async fn dial(endpoint: &Endpoint, node_addr: NodeAddr) -> Result<OutgoingCall> {
+    let call_state = CallState::new();
+    let span = call_span(call_state.call_id);
+    connect_call(endpoint, node_addr, call_state).instrument(span).await
+}
+
+async fn connect_call(endpoint: &Endpoint, node_addr: NodeAddr, call_state: CallState) -> Result<OutgoingCall> {
+    info!("Dialing {} ...", node_addr.node_id.fmt_short());
+    let conn = connect(endpoint, node_addr).await?;
+    debug!("Connected. Opening bi-directional stream...");
+    let (mut send, recv) = conn.open_bi().await?;
     
-    // Listen for hangup signal from peer
-    let peer_hangup_monitor = async move {
-        println!("👂 [CALL-{}] Starting peer hangup monitor...", call_id);
-        let mut hangup_buf = [0u8; 6]; // "HANGUP" length
-        match recv.read_exact(&mut hangup_buf).await {
-            Ok(_) if &hangup_buf == b"HANGUP" => {
-                println!("📞 [CALL-{}] Received HANGUP signal from peer!", call_id);
-                true
-            }
-            Ok(_) => {
-                println!("📞 [CALL-{}] Received unexpected data from peer", call_id);
-                false
+    // Send incoming call signal to trigger caller's ringtone
+    debug!("Sending incoming call signal...");
+    send_signal(&mut send, &Signal::IncomingCall).await?;
+    info!("Call initiated - caller should be ringing now");
+    
+    CallManager::register_outgoing_call(call_state.call_id);
+    let session = CallSession::new(call_state, CallDirection::Outgoing, endpoint.clone(), conn.clone(), send, SignalReader::new(recv));
+    Ok(OutgoingCall { endpoint: endpoint.clone(), conn, session })
+}
+
+impl OutgoingCall {
+    pub fn call_id(&self) -> u128 {
+        self.session.state.call_id
+    }
+
+    // This call is the consultation leg of an attended transfer of `held_call`
+    fn consulting(mut self, held_call: u128, target: NodeTicket) -> Self {
+        self.session.consult = Some((held_call, target));
+        self
+    }
+
+    pub async fn run(self) -> Result<CallOutcome> {
+        let span = call_span(self.call_id());
+        self.run_call().instrument(span).await
+    }
+
+    async fn run_call(self) -> Result<CallOutcome> {
+        let call_id = self.call_id();
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let stats_logger = spawn_stats_logger(self.endpoint.clone(), self.conn.clone(), call_id);
+        let chat = self.session.chat_log();
+        let result = self.session.run().await;
+        
+        stats_logger.abort();
+        let record = CallRecord::finish(call_id, &self.endpoint, &self.conn, started_at, started, &chat);
+        if CallManager::stats_enabled() {
+            info!("Call summary: {} over {:?}", record.stats, record.duration);
+        }
+        CallManager::record_call(record);
+        CallManager::release_call(call_id);
+        result
+    }
+
+    // Run the call in the background, e.g. after a transfer
+    pub fn spawn(self) {
+        let span = call_span(self.call_id());
+        tokio::spawn(async move {
+            if let Err(e) = self.run_call().await {
+                error!("Call handling error: {}", e);
             }
-            Err(e) => {
-                println!("📞 [CALL-{}] Connection lost: {}", call_id, e);
-                false
+        }.instrument(span));
+    }
+}
+
+/// Which side of the call this process is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallDirection {
+    /// We are being called and ring until the user answers
+    Incoming,
+    /// We dialed and wait for the other side to answer
+    Outgoing,
+}
+
+/// Drives one call from ringing to hangup on either side.
+///
+/// Both sides run the same loop: signals from the remote move the call
+/// through its [`CallPhase`]s, and so do [`CallCommand`]s from the local user.
+pub struct CallSession {
+    state: CallState,
+    direction: CallDirection,
+    // Used to place new calls when transferring
+    endpoint: Endpoint,
+    conn: Connection,
+    // Who's on the other end, looked up in the contacts once per call
+    remote_name: String,
+    send: SendStream,
+    signals: SignalReader,
+    ringtone: Option<AudioManager>,
+    hold_music: Option<AudioManager>,
+    controls: VoiceControls,
+    voice: Option<VoiceLink>,
+    chat: Option<ChatLink>,
+    chat_log: ChatLog,
+    // Files we offered, and files the other side offered that we haven't downloaded
+    sent_files: Vec<FileOffer>,
+    offered_files: Vec<FileOffer>,
+    // The recording we asked the other side to allow, and whether they're recording us
+    recording_request: Option<RecordingMode>,
+    remote_recording: bool,
+    // Hashes of finished downloads, to confirm them to the other side
+    downloaded_tx: tokio::sync::mpsc::UnboundedSender<iroh_blobs::Hash>,
+    downloaded_rx: tokio::sync::mpsc::UnboundedReceiver<iroh_blobs::Hash>,
+    // The held call and target of an attended transfer this call consults
+    consult: Option<(u128, NodeTicket)>,
+}
+
+impl CallSession {
+    pub fn new(state: CallState, direction: CallDirection, endpoint: Endpoint, conn: Connection, send: SendStream, signals: SignalReader) -> Self {
+        let (downloaded_tx, downloaded_rx) = tokio::sync::mpsc::unbounded_channel();
+        let controls = VoiceControls::new();
+        controls.set_volume(CallManager::audio_settings().volume);
+        let remote_name = conn
+            .remote_node_id()
+            .map(|node_id| ContactBook::load_or_default().display_name(node_id))
+            .unwrap_or_else(|_| "The other side".to_string());
+        Self {
+            state,
+            direction,
+            endpoint,
+            conn,
+            remote_name,
+            send,
+            signals,
+            ringtone: None,
+            hold_music: None,
+            controls,
+            voice: None,
+            chat: None,
+            chat_log: ChatLog::default(),
+            sent_files: Vec::new(),
+            offered_files: Vec::new(),
+            recording_request: None,
+            remote_recording: false,
+            downloaded_tx,
+            downloaded_rx,
+            consult: None,
+        }
+    }
+
+    // Stop `ringtone` as soon as the call is answered or ends
+    pub fn with_ringtone(mut self, ringtone: AudioManager) -> Self {
+        self.ringtone = Some(ringtone);
+        self
+    }
+
+    // The call's chat messages, which outlive the session for the call history
+    pub fn chat_log(&self) -> ChatLog {
+        self.chat_log.clone()
+    }
+
+    pub async fn run(mut self) -> Result<CallOutcome> {
+        let call_id = self.state.call_id;
+        let mut commands = subscribe_commands();
+        let mut hangup_rx = init_hangup_system();
+        let dial_deadline = tokio::time::Instant::now() + CallManager::timeouts().dial();
+        let forwarding = CallManager::forwarding();
+        let no_answer_deadline = tokio::time::Instant::now() + forwarding.no_answer_timeout;
+        
+        debug!("Starting call loop - monitoring for events...");
+        if let Ok(remote) = self.conn.remote_node_id() {
+            emit_event(call_id, CallEvent::Started { remote, direction: self.direction });
+        }
+        let outcome = loop {
+            let ringing_out = self.direction == CallDirection::Outgoing && self.state.phase == CallPhase::Ringing;
+            let ringing_in = self.direction == CallDirection::Incoming
+                && matches!(self.state.phase, CallPhase::Ringing | CallPhase::Waiting);
+            tokio::select! {
+                signal = self.signals.next() => match signal {
+                    Ok(Some(signal)) => {
+                        if let Some(outcome) = self.on_signal(signal).await? {
+                            break outcome;
+                        }
+                    }
+                    Ok(None) => {
+                        info!("Remote side closed the call");
+                        break CallOutcome::RemoteHungUp;
+                    }
+                    Err(e) => {
+                        warn!("Connection lost: {}", e);
+                        break CallOutcome::ConnectionLost;
+                    }
+                },
+                command = commands.recv() => {
+                    if let Ok((target, command)) = command {
+                        if target == call_id {
+                            if let Some(outcome) = self.on_command(command).await? {
+                                break outcome;
+                            }
+                        }
+                    }
+                }
+                Some(hash) = self.downloaded_rx.recv() => {
+                    send_signal(&mut self.send, &Signal::FileReceived(hash)).await?;
+                }
+                _ = hangup_rx.recv() => {
+                    info!("Hangup signal received - terminating call...");
+                    self.hangup().await?;
+                    break CallOutcome::HungUp;
+                }
+                _ = tokio::time::sleep_until(dial_deadline), if ringing_out => {
+                    info!("Call timed out - no answer");
+                    self.hangup().await?;
+                    break CallOutcome::NoAnswer;
+                }
+                _ = tokio::time::sleep_until(no_answer_deadline), if ringing_in && forwarding.on_no_answer.is_some() => {
+                    let target = forwarding.on_no_answer.clone().expect("checked above");
+                    info!("No answer after {:?}", forwarding.no_answer_timeout);
+                    self.stop_audio();
+                    forward_call(&mut self.send, &target).await?;
+                    break CallOutcome::Forwarded(target);
+                }
             }
+        };
+        
+        // Properly close streams to clean up connection
+        debug!("Cleaning up call session...");
+        self.stop_audio();
+        if let Some(chat) = self.chat.take() {
+            chat.stop();
         }
-    };
-    
-    // Race between audio completion, peer hangup, local hangup, and Ctrl+C
-    println!("🔄 [CALL-{}] Starting select! loop - monitoring for events...", call_id);
-    tokio::select! {
-        result = audio_task => {
-            match result {
-                Ok(Ok(())) => println!("🎵 [CALL-{}] Audio task completed normally", call_id),
-                Ok(Err(e)) => println!("❌ [CALL-{}] Audio task error: {}", call_id, e),
-                Err(e) => println!("❌ [CALL-{}] Audio task panic: {}", call_id, e),
-            }
-        }
-        hangup_received = peer_hangup_monitor => {
-            if hangup_received {
-                println!("🔇 [CALL-{}] Peer hung up - stopping ringtone!", call_id);
-                audio_manager.stop(); // Stop the audio immediately
+        self.stop_recording();
+        let messages = self.chat_log.messages().len();
+        if messages > 0 {
+            info!("{} chat message(s) saved with the call history", messages);
+        }
+        if self.state.phase != CallPhase::Ended {
+            self.state.set_phase(CallPhase::Ended);
+        }
+        let _ = self.send.finish();
+        
+        // Wait a moment for cleanup to complete
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        debug!("Call cleanup completed");
+        Ok(outcome)
+    }
+
+    // Returns how the call ended once it's over
+    async fn on_signal(&mut self, signal: Signal) -> Result<Option<CallOutcome>> {
+        let call_id = self.state.call_id;
+        match (signal, self.state.phase) {
+            (Signal::Answer, CallPhase::Ringing) if self.direction == CallDirection::Outgoing => {
+                info!("Call answered");
+                self.start_voice();
+            }
+            (Signal::Hold, CallPhase::Active) => {
+                info!("You have been put on hold");
+                self.controls.set_paused(true);
+                self.start_hold_music();
+                self.state.set_phase(CallPhase::OnHold { by_us: false });
+            }
+            (Signal::Resume, CallPhase::OnHold { by_us: false }) => {
+                info!("Call resumed by the other side");
+                self.stop_hold_music();
+                self.controls.set_paused(false);
+                self.state.set_phase(CallPhase::Active);
+            }
+            (Signal::Roster(nodes), _) => {
+                let me = self.endpoint.node_id();
+                let contacts = ContactBook::load_or_default();
+                let names: Vec<String> = nodes
+                    .into_iter()
+                    .map(|node| if node == me { "you".to_string() } else { contacts.display_name(node) })
+                    .collect();
+                info!("In this conference: {}", names.join(", "));
+            }
+            (Signal::FileOffer(offer), _) => {
+                info!("{} wants to send you {} ({}) - press 'g' to download it", self.remote_name(), offer.file_name(), format_size(offer.size));
+                self.offered_files.push(offer);
+            }
+            (Signal::FileReceived(hash), _) => {
+                if let Some(offer) = self.sent_files.iter().find(|offer| offer.hash == hash) {
+                    info!("{} downloaded {}", self.remote_name(), offer.file_name());
+                }
+            }
+            (Signal::Dtmf(digit), CallPhase::Active) => {
+                info!("{} pressed {}", self.remote_name(), digit);
+                emit_event(call_id, CallEvent::Dtmf(digit));
+            }
+            (Signal::Recording, _) => {
+                if CallManager::recording_allowed() {
+                    send_signal(&mut self.send, &Signal::RecordingAllowed).await?;
+                    self.remote_recording = true;
+                    info!("{} is recording this call", self.remote_name());
+                } else {
+                    send_signal(&mut self.send, &Signal::RecordingRefused).await?;
+                    info!("Refused {}'s request to record this call", self.remote_name());
+                }
+            }
+            (Signal::RecordingAllowed, _) if self.recording_request.is_some() => {
+                let mode = self.recording_request.take().expect("checked above");
+                match CallRecorder::create(call_id, mode) {
+                    Ok(recorder) => {
+                        info!("Recording to {}", recorder.path().display());
+                        self.controls.start_recording(recorder);
+                    }
+                    Err(e) => {
+                        warn!("Couldn't start recording: {}", e);
+                        send_signal(&mut self.send, &Signal::RecordingStopped).await?;
+                    }
+                }
+            }
+            (Signal::RecordingRefused, _) if self.recording_request.is_some() => {
+                self.recording_request = None;
+                info!("{} doesn't allow recording", self.remote_name());
+            }
+            (Signal::RecordingStopped, _) if self.remote_recording => {
+                self.remote_recording = false;
+                info!("{} stopped recording", self.remote_name());
+            }
+            (Signal::Muted, _) => {
+                info!("{} is muted", self.remote_name());
+            }
+            (Signal::Unmuted, _) => {
+                info!("{} unmuted", self.remote_name());
+            }
+            (Signal::Hangup, _) => {
+                info!("Received HANGUP signal from peer!");
+                self.stop_audio();
+                self.state.set_phase(CallPhase::Ended);
                 
                 // Send acknowledgment to peer
-                println!("📤 [CALL-{}] Sending hangup acknowledgment to peer...", call_id);
-                if let Err(e) = send.write_all(b"HANGUP_ACK").await {
-                    println!("⚠️ [CALL-{}] Failed to send hangup acknowledgment: {}", call_id, e);
+                debug!("Sending hangup acknowledgment to peer...");
+                if let Err(e) = send_signal(&mut self.send, &Signal::HangupAck).await {
+                    warn!("Failed to send hangup acknowledgment: {}", e);
                 } else {
-                    println!("✅ [CALL-{}] Hangup acknowledgment sent", call_id);
+                    debug!("Hangup acknowledgment sent");
                 }
-                
-                let _ = call_hangup_tx.send(()); // Signal this call to stop
-            } else {
-                println!("🔇 [CALL-{}] Peer hangup monitor returned false", call_id);
+                return Ok(Some(CallOutcome::RemoteHungUp));
+            }
+            (Signal::Busy, _) => {
+                info!("Line is busy - try again later");
+                return Ok(Some(CallOutcome::Busy));
+            }
+            (Signal::DoNotDisturb, _) => {
+                info!("{} doesn't want to be disturbed - try again later", self.remote_name());
+                return Ok(Some(CallOutcome::DoNotDisturb));
+            }
+            (Signal::Redirect(ticket), CallPhase::Ringing) if self.direction == CallDirection::Outgoing => {
+                info!("Call forwarded to {}", short_name(&ticket));
+                return Ok(Some(CallOutcome::Redirected(ticket)));
+            }
+            (Signal::Transfer(ticket), CallPhase::Active | CallPhase::OnHold { by_us: false }) => {
+                info!("{} is transferring you to {}", self.remote_name(), short_name(&ticket));
+                // Reach the target before dropping this call, so a failed transfer loses nothing
+                match dial(&self.endpoint, ticket.node_addr().clone()).await {
+                    Ok(call) => {
+                        call.spawn();
+                        self.hangup().await?;
+                        return Ok(Some(CallOutcome::Transferred(ticket)));
+                    }
+                    Err(e) => {
+                        warn!("Couldn't reach {} - staying on this call: {}", short_name(&ticket), e);
+                    }
+                }
+            }
+            (signal, phase) => {
+                warn!("Ignoring {} while {}", signal, phase);
+            }
+        }
+        Ok(None)
+    }
+
+    // Returns how the call ended once it's over
+    async fn on_command(&mut self, command: CallCommand) -> Result<Option<CallOutcome>> {
+        let call_id = self.state.call_id;
+        match (command, self.state.phase) {
+            (CallCommand::Answer, CallPhase::Ringing) if self.direction == CallDirection::Incoming => {
+                self.answer().await?;
+            }
+            (CallCommand::Decline, CallPhase::Ringing | CallPhase::Waiting) if self.direction == CallDirection::Incoming => {
+                self.stop_audio();
+                send_signal(&mut self.send, &Signal::Busy).await?;
+                info!("Call declined");
+                return Ok(Some(CallOutcome::HungUp));
+            }
+            (CallCommand::Hold, CallPhase::Active) => self.hold().await?,
+            (CallCommand::Resume, CallPhase::OnHold { by_us: true }) => self.resume().await?,
+            (CallCommand::Hangup, _) => {
+                self.hangup().await?;
+                return Ok(Some(CallOutcome::HungUp));
+            }
+            (CallCommand::Mute, _) => self.set_muted(true).await?,
+            (CallCommand::Unmute, _) => self.set_muted(false).await?,
+            (CallCommand::ToggleMute, _) => self.set_muted(!self.controls.is_muted()).await?,
+            (CallCommand::VolumeUp, _) => {
+                self.change_volume(self.controls.volume().saturating_add(VOLUME_STEP));
+            }
+            (CallCommand::VolumeDown, _) => {
+                self.change_volume(self.controls.volume().saturating_sub(VOLUME_STEP));
+            }
+            (CallCommand::SetVolume(percent), _) => self.change_volume(percent),
+            (CallCommand::Switch, phase) => match CallManager::other_call(call_id) {
+                Some(other) => {
+                    if phase == CallPhase::Active {
+                        self.hold().await?;
+                    }
+                    CallManager::set_focus(other);
+                    send_command(CallCommand::SwitchTo(other));
+                }
+                None => warn!("No other call to switch to"),
+            },
+            (CallCommand::SwitchTo(_), phase) => {
+                info!("Switched to this call");
+                match phase {
+                    CallPhase::Waiting | CallPhase::Ringing if self.direction == CallDirection::Incoming => {
+                        self.answer().await?;
+                    }
+                    CallPhase::OnHold { by_us: true } => self.resume().await?,
+                    _ => {}
+                }
+            }
+            (CallCommand::Transfer(target), CallPhase::Active | CallPhase::OnHold { by_us: true }) => {
+                send_signal(&mut self.send, &Signal::Transfer(target.clone())).await?;
+                info!("Transferring {} to {} - the call ends once they're through", self.remote_name(), short_name(&target));
+            }
+            (CallCommand::ConsultTransfer(target), phase @ (CallPhase::Active | CallPhase::OnHold { by_us: true })) => {
+                if phase == CallPhase::Active {
+                    self.hold().await?;
+                }
+                match dial(&self.endpoint, target.node_addr().clone()).await {
+                    Ok(call) => {
+                        info!("Press 'x' to hand this call over to {}, or 'q' to hang up on them", short_name(&target));
+                        call.consulting(call_id, target).spawn();
+                    }
+                    Err(e) => {
+                        warn!("Couldn't reach {} - press 'r' to resume: {}", short_name(&target), e);
+                    }
+                }
+            }
+            (CallCommand::CompleteTransfer, _) if self.consult.is_some() => {
+                let (held_call, target) = self.consult.take().expect("checked above");
+                // Free the target's line before the transferred caller dials it
+                self.hangup().await?;
+                CallManager::set_focus(held_call);
+                send_command(CallCommand::Transfer(target));
+                return Ok(Some(CallOutcome::HungUp));
+            }
+            (CallCommand::Chat(text), _) if self.chat.is_some() => {
+                self.chat.as_ref().expect("checked above").send(text);
+            }
+            (CallCommand::SendFile(path), CallPhase::Active | CallPhase::OnHold { .. }) => match share_file(&path).await {
+                Ok(offer) => {
+                    send_signal(&mut self.send, &Signal::FileOffer(offer.clone())).await?;
+                    info!("Offered {} ({}) to {}", offer.file_name(), format_size(offer.size), self.remote_name());
+                    self.sent_files.push(offer);
+                }
+                Err(e) => warn!("Couldn't share {}: {}", path.display(), e),
+            },
+            (CallCommand::ToggleRecording(_), _) if self.controls.is_recording() => {
+                self.stop_recording();
+                send_signal(&mut self.send, &Signal::RecordingStopped).await?;
+            }
+            (CallCommand::ToggleRecording(_), _) if self.recording_request.is_some() => {
+                info!("Still waiting for {} to allow recording", self.remote_name());
+            }
+            (CallCommand::ToggleRecording(mode), CallPhase::Active | CallPhase::OnHold { .. }) => {
+                send_signal(&mut self.send, &Signal::Recording).await?;
+                self.recording_request = Some(mode);
+                info!("Asking {} to allow recording...", self.remote_name());
+            }
+            (CallCommand::Dtmf(digits), CallPhase::Active) => {
+                for digit in digits.chars() {
+                    send_signal(&mut self.send, &Signal::Dtmf(digit)).await?;
+                }
+                if let Some(voice) = self.voice.as_ref().filter(|_| CallManager::dtmf_tones_enabled()) {
+                    voice.send_tones(&digits);
+                }
+                info!("Pressed {}", digits);
+            }
+            (CallCommand::AcceptFile, _) => match self.offered_files.pop() {
+                Some(offer) => self.download(offer)?,
+                None => warn!("No file to download"),
+            },
+            (CallCommand::Focus(_), phase) => match phase {
+                // The call we were on ended - a waiting call now rings normally
+                CallPhase::Waiting => {
+                    self.ringtone = Some(play_caller_ringtone(&ringtone_for(&self.conn), call_id).await?);
+                    self.state.set_phase(CallPhase::Ringing);
+                }
+                CallPhase::OnHold { by_us: true } => {
+                    info!("This call is still on hold - press 'r' to resume it");
+                }
+                _ => {}
+            },
+            (command, phase) => {
+                warn!("Can't {} while {}", command, phase);
             }
         }
-        _ = call_hangup_rx.recv() => {
-            println!("🔇 [CALL-{}] Call-specific hangup signal received - stopping ringtone!", call_id);
-            audio_manager.stop(); // Stop the audio immediately
+        Ok(None)
+    }
+
+    async fn answer(&mut self) -> Result<()> {
+        if let Some(ringtone) = self.ringtone.take() {
+            ringtone.stop();
         }
-        _ = tokio::signal::ctrl_c() => {
-            println!("🔇 [CALL-{}] Ctrl+C pressed - hanging up call!", call_id);
-            audio_manager.stop(); // Stop the audio immediately
-            let _ = call_hangup_tx.send(()); // Signal this call to stop
+        send_signal(&mut self.send, &Signal::Answer).await?;
+        info!("Call answered");
+        self.start_voice();
+        Ok(())
+    }
+
+    async fn hold(&mut self) -> Result<()> {
+        send_signal(&mut self.send, &Signal::Hold).await?;
+        info!("Call on hold - the other side hears hold music");
+        self.controls.set_paused(true);
+        self.state.set_phase(CallPhase::OnHold { by_us: true });
+        Ok(())
+    }
+
+    async fn resume(&mut self) -> Result<()> {
+        send_signal(&mut self.send, &Signal::Resume).await?;
+        info!("Call resumed");
+        self.controls.set_paused(false);
+        self.state.set_phase(CallPhase::Active);
+        Ok(())
+    }
+
+    // Send HANGUP and give the remote a moment to acknowledge it
+    async fn hangup(&mut self) -> Result<()> {
+        self.stop_audio();
+        debug!("Hanging up and waiting for the other side to acknowledge...");
+        if hangup_and_wait(&mut self.send, &mut self.signals).await? {
+            debug!("Hangup acknowledged - terminating cleanly");
+        } else {
+            warn!("No acknowledgment received - terminating anyway");
         }
+        Ok(())
     }
-    
-    // Properly close streams to clean up connection
-    println!("🧹 [CALL-{}] Cleaning up call session...", call_id);
-    drop(send);
-    // recv is already consumed by peer_hangup_monitor
-    
-    // Wait a moment for cleanup to complete
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    println!("✅ [CALL-{}] Call cleanup completed", call_id);
-    
-    Ok(())
-}
 
-pub async fn send_hangup_to_caller(send: &mut iroh::endpoint::SendStream) -> Result<()> {
-    println!("📞 Sending hangup signal to caller...");
-    if let Err(e) = send.write_all(b"HANGUP").await {
-        println!("❌ Failed to send hangup signal: {}", e);
-        return Err(e.into());
+    fn stop_recording(&mut self) {
+        match self.controls.stop_recording() {
+            Some(Ok(path)) => info!("Recording saved to {}", path.display()),
+            Some(Err(e)) => warn!("Couldn't save the recording: {}", e),
+            None => {}
+        }
+    }
+
+    // Fetch an offered file in the background so the call carries on meanwhile
+    fn download(&mut self, offer: FileOffer) -> Result<()> {
+        let endpoint = self.endpoint.clone();
+        let provider = self.conn.remote_node_id()?;
+        let downloaded = self.downloaded_tx.clone();
+        tokio::spawn(async move {
+            match download_file(&endpoint, provider, &offer).await {
+                Ok(path) => {
+                    info!("Saved {} to {}", offer.file_name(), path.display());
+                    let _ = downloaded.send(offer.hash);
+                }
+                Err(e) => warn!("Couldn't download {}: {}", offer.file_name(), e),
+            }
+        }.in_current_span());
+        Ok(())
+    }
+
+    // The call is answered: start two-way audio and the chat
+    fn start_voice(&mut self) {
+        self.state.set_phase(CallPhase::Active);
+        if self.voice.is_none() {
+            self.voice = Some(VoiceLink::start(self.conn.clone(), self.controls.clone(), self.state.call_id));
+        }
+        if self.chat.is_none() {
+            let chat = ChatLink::start(self.conn.clone(), self.direction, self.chat_log.clone(), self.remote_name.clone());
+            self.chat = Some(chat);
+        }
+    }
+
+    async fn set_muted(&mut self, muted: bool) -> Result<()> {
+        self.controls.set_muted(muted);
+        emit_event(self.state.call_id, CallEvent::Muted(muted));
+        if muted {
+            info!("Microphone muted");
+            send_signal(&mut self.send, &Signal::Muted).await
+        } else {
+            info!("Microphone unmuted");
+            send_signal(&mut self.send, &Signal::Unmuted).await
+        }
+    }
+
+    fn change_volume(&mut self, percent: u8) {
+        let percent = self.controls.set_volume(percent);
+        info!("Volume: {}%", percent);
+    }
+
+    fn remote_name(&self) -> &str {
+        &self.remote_name
+    }
+
+    fn start_hold_music(&mut self) {
+        let track = CallManager::get_hold_music();
+        let player = AudioManager::new();
+        match player.play_hold_music_async(&track, self.state.call_id) {
+            Ok(_ready) => self.hold_music = Some(player),
+            Err(e) => warn!("Couldn't play hold music: {}", e),
+        }
+    }
+
+    fn stop_hold_music(&mut self) {
+        if let Some(player) = self.hold_music.take() {
+            player.stop();
+        }
+    }
+
+    fn stop_audio(&mut self) {
+        if let Some(ringtone) = self.ringtone.take() {
+            ringtone.stop();
+        }
+        self.stop_hold_music();
+        if let Some(voice) = self.voice.take() {
+            voice.stop();
+        }
     }
-    println!("✅ Hangup signal sent successfully");
-    Ok(())
 }
+
@@ -0,0 +1,453 @@
+use anyhow::Result;
+use iroh::endpoint::{Connection, SendStream};
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use ringbuf::HeapProd;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::call::close_and_wait;
+use crate::connect::connect;
+use crate::contacts::ContactBook;
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+use crate::roster::{run_member, Roster};
+use crate::voice::{decode_frame, encode_frame, queue_playback};
+
+// Longest anyone can hold the floor before the hub frees it for others
+pub const MAX_TALK_TIME: Duration = Duration::from_secs(30);
+
+// Most members one hub relays to, not counting the hub
+pub const MAX_MEMBERS: usize = 32;
+
+/// Everything needed to join a push-to-talk channel: its name and the hub
+/// node members connect through. Written as `<name>:<node ticket>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelTicket {
+    pub name: String,
+    pub hub: NodeTicket,
+}
+
+impl fmt::Display for ChannelTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.hub)
+    }
+}
+
+impl FromStr for ChannelTicket {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, hub) = s
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Channel tickets look like <name>:<node ticket>"))?;
+        let hub = hub.parse().map_err(|_| anyhow::anyhow!("Invalid node Ticket format"))?;
+        Ok(Self { name: name.to_string(), hub })
+    }
+}
+
+/// What the local user can do on a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCommand {
+    /// Ask for the floor, or give it back if we're talking
+    Talk,
+    Roster,
+    Leave,
+}
+
+// Feed received voice frames to the speaker from one place
+pub fn spawn_speaker(mut producer: HeapProd<f32>) -> mpsc::UnboundedSender<Vec<i16>> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<i16>>();
+    tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            queue_playback(&mut producer, &frame);
+        }
+    });
+    tx
+}
+
+fn print_roster(name: &str, nodes: &[NodeId], me: NodeId, contacts: &ContactBook) {
+    let names: Vec<String> = nodes
+        .iter()
+        .map(|node| if *node == me { "you".to_string() } else { contacts.display_name(*node) })
+        .collect();
+    info!(channel = %name, "On the channel: {}", names.join(", "));
+}
+
+/// The node a push-to-talk channel runs through.
+///
+/// Members connect to the hub on the radyo ALPN with `JOIN_CHANNEL <name>`.
+/// The hub hands out the floor one member at a time and relays only the
+/// floor holder's voice to everyone else. The hub is a member too.
+#[derive(Debug, Clone)]
+pub struct ChannelHub {
+    name: String,
+    endpoint: Endpoint,
+    members: Roster<()>,
+    // Loaded once when the hub starts, to name members in the log
+    contacts: Arc<ContactBook>,
+    floor: Arc<Mutex<Option<Floor>>>,
+    // The hub's own speaker
+    speaker: mpsc::UnboundedSender<Vec<i16>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Floor {
+    holder: NodeId,
+    since: Instant,
+}
+
+impl ProtocolHandler for ChannelHub {
+    async fn accept(&self, conn: Connection) -> Result<(), AcceptError> {
+        let hub = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = hub.accept_member(conn).await {
+                warn!(channel = %hub.name, "Couldn't add member: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+impl ChannelHub {
+    pub fn new(name: String, endpoint: Endpoint, speaker: mpsc::UnboundedSender<Vec<i16>>) -> Self {
+        Self {
+            name,
+            members: Roster::with_limit(endpoint.node_id(), MAX_MEMBERS),
+            contacts: Arc::new(ContactBook::load_or_default()),
+            endpoint,
+            floor: Arc::new(Mutex::new(None)),
+            speaker,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Everyone on the channel, hub first.
+    pub fn roster(&self) -> Vec<NodeId> {
+        self.members.node_ids()
+    }
+
+    pub fn holder(&self) -> Option<NodeId> {
+        self.floor.lock().unwrap().map(|floor| floor.holder)
+    }
+
+    async fn accept_member(&self, conn: Connection) -> Result<()> {
+        let (mut send, recv) = conn.accept_bi().await?;
+        let mut signals = SignalReader::new(recv);
+        let Some(Signal::JoinChannel(name)) = signals.next().await? else {
+            return Ok(());
+        };
+        // Taking the place before answering means two joins can't both get the last one
+        let node_id = conn.remote_node_id()?;
+        let outgoing = if name == self.name { self.members.add(node_id, conn.clone(), ()) } else { None };
+        let Some(outgoing) = outgoing else {
+            info!(channel = %self.name, "Turned away a member asking for channel {}", name);
+            send_signal(&mut send, &Signal::Busy).await?;
+            close_and_wait(&mut send).await;
+            return Ok(());
+        };
+        if let Err(e) = send_signal(&mut send, &Signal::Answer).await {
+            self.members.remove(node_id);
+            return Err(e);
+        }
+
+        if let Some(holder) = self.holder() {
+            self.send_to(node_id, Signal::FloorTaken(holder));
+        }
+        info!(channel = %self.name, "{} joined", self.contacts.display_name(node_id));
+
+        tokio::spawn(self.clone().relay_voice(node_id, conn));
+        tokio::spawn(self.clone().run_member(node_id, send, signals, outgoing));
+        self.broadcast_roster();
+        Ok(())
+    }
+
+    // Pass the floor holder's voice on to everyone else
+    async fn relay_voice(self, node_id: NodeId, conn: Connection) {
+        while let Ok(datagram) = conn.read_datagram().await {
+            if self.holder() != Some(node_id) {
+                continue;
+            }
+            for member in self.members.lock().iter().filter(|m| m.node_id != node_id) {
+                let _ = member.conn.send_datagram(datagram.clone());
+            }
+            if let Some((_seq, samples)) = decode_frame(&datagram) {
+                let _ = self.speaker.send(samples);
+            }
+        }
+    }
+
+    // Carries one member's control stream until they leave
+    async fn run_member(
+        self,
+        node_id: NodeId,
+        send: SendStream,
+        signals: SignalReader,
+        outgoing: mpsc::UnboundedReceiver<Signal>,
+    ) {
+        run_member(send, signals, outgoing, |signal| match signal {
+            Signal::FloorRequest => {
+                self.request_floor(node_id);
+            }
+            Signal::FloorRelease => self.release_floor(node_id),
+            _ => {}
+        })
+        .await;
+        self.members.remove(node_id);
+        info!(channel = %self.name, "{} left", self.contacts.display_name(node_id));
+        self.release_floor(node_id);
+        self.broadcast_roster();
+    }
+
+    fn send_to(&self, node_id: NodeId, signal: Signal) {
+        self.members.send_to(node_id, signal);
+    }
+
+    fn broadcast(&self, signal: Signal, except: Option<NodeId>) {
+        self.members.broadcast(signal, except);
+    }
+
+    fn broadcast_roster(&self) {
+        let roster = self.roster();
+        self.broadcast(Signal::Roster(roster.clone()), None);
+        print_roster(&self.name, &roster, self.endpoint.node_id(), &self.contacts);
+    }
+
+    /// Gives `node_id` the floor unless someone else is talking.
+    pub fn request_floor(&self, node_id: NodeId) -> bool {
+        let me = self.endpoint.node_id();
+        let refused_by = {
+            let mut floor = self.floor.lock().unwrap();
+            match *floor {
+                Some(current) if current.holder != node_id => Some(current.holder),
+                _ => {
+                    *floor = Some(Floor { holder: node_id, since: Instant::now() });
+                    None
+                }
+            }
+        };
+
+        if let Some(holder) = refused_by {
+            if node_id == me {
+                info!(channel = %self.name, "{} has the floor - wait for them to finish", self.contacts.display_name(holder));
+            } else {
+                self.send_to(node_id, Signal::FloorTaken(holder));
+            }
+            return false;
+        }
+        if node_id == me {
+            info!(channel = %self.name, "You're on air - press Enter to stop talking");
+        } else {
+            self.send_to(node_id, Signal::FloorGranted);
+            info!(channel = %self.name, "{} is talking", self.contacts.display_name(node_id));
+        }
+        self.broadcast(Signal::FloorTaken(node_id), Some(node_id));
+        true
+    }
+
+    /// Frees the floor if `node_id` holds it.
+    pub fn release_floor(&self, node_id: NodeId) {
+        {
+            let mut floor = self.floor.lock().unwrap();
+            match *floor {
+                Some(current) if current.holder == node_id => *floor = None,
+                _ => return,
+            }
+        }
+        self.broadcast(Signal::FloorFree, None);
+        info!(channel = %self.name, "Channel is free - press Enter to talk");
+    }
+
+    // Nobody keeps the floor forever, e.g. after forgetting to press Enter again
+    async fn expire_floor(self) {
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tick.tick().await;
+            let expired = self
+                .floor
+                .lock()
+                .unwrap()
+                .filter(|floor| floor.since.elapsed() >= MAX_TALK_TIME);
+            if let Some(floor) = expired {
+                info!(channel = %self.name, "{} talked for {:?} - freeing the floor", self.contacts.display_name(floor.holder), MAX_TALK_TIME);
+                self.release_floor(floor.holder);
+            }
+        }
+    }
+
+    /// Runs the hub's own side of the channel until the user leaves.
+    pub async fn run(&self, mut mic: mpsc::Receiver<Vec<i16>>, mut commands: mpsc::UnboundedReceiver<ChannelCommand>) {
+        let me = self.endpoint.node_id();
+        let hub = self.clone();
+        let transmitter = tokio::spawn(async move {
+            let mut seq: u32 = 0;
+            while let Some(frame) = mic.recv().await {
+                if hub.holder() != Some(me) {
+                    continue;
+                }
+                let datagram = encode_frame(seq, &frame);
+                for member in hub.members.lock().iter() {
+                    let _ = member.conn.send_datagram(datagram.clone());
+                }
+                seq = seq.wrapping_add(1);
+            }
+        });
+        let expiry = tokio::spawn(self.clone().expire_floor());
+
+        loop {
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(ChannelCommand::Talk) => {
+                        if self.holder() == Some(me) {
+                            self.release_floor(me);
+                        } else {
+                            self.request_floor(me);
+                        }
+                    }
+                    Some(ChannelCommand::Roster) => print_roster(&self.name, &self.roster(), me, &self.contacts),
+                    Some(ChannelCommand::Leave) | None => break,
+                },
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        transmitter.abort();
+        expiry.abort();
+        self.members.hang_up_all().await;
+    }
+}
+
+/// Joins the channel through its hub and runs it until the user leaves.
+pub async fn join_channel(
+    endpoint: &Endpoint,
+    ticket: ChannelTicket,
+    mut mic: mpsc::Receiver<Vec<i16>>,
+    speaker: mpsc::UnboundedSender<Vec<i16>>,
+    mut commands: mpsc::UnboundedReceiver<ChannelCommand>,
+) -> Result<()> {
+    let name = ticket.name.clone();
+    let me = endpoint.node_id();
+    let contacts = ContactBook::load_or_default();
+    let conn = connect(endpoint, ticket.hub.node_addr().clone()).await?;
+    let (mut send, recv) = conn.open_bi().await?;
+    let mut signals = SignalReader::new(recv);
+    send_signal(&mut send, &Signal::JoinChannel(name.clone())).await?;
+    match signals.next().await? {
+        Some(Signal::Answer) => info!(channel = %name, "Joined - press Enter to talk"),
+        Some(Signal::Busy) => anyhow::bail!("The hub doesn't run channel {} or it is full", name),
+        _ => anyhow::bail!("The hub closed the connection"),
+    }
+
+    // Our voice only goes out while we hold the floor
+    let talking = Arc::new(AtomicBool::new(false));
+    let transmitter = {
+        let conn = conn.clone();
+        let talking = talking.clone();
+        tokio::spawn(async move {
+            let mut seq: u32 = 0;
+            while let Some(frame) = mic.recv().await {
+                if !talking.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if conn.send_datagram(encode_frame(seq, &frame)).is_err() {
+                    break;
+                }
+                seq = seq.wrapping_add(1);
+            }
+        })
+    };
+    let receiver = {
+        let conn = conn.clone();
+        let talking = talking.clone();
+        tokio::spawn(async move {
+            while let Ok(datagram) = conn.read_datagram().await {
+                if talking.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if let Some((_seq, samples)) = decode_frame(&datagram) {
+                    let _ = speaker.send(samples);
+                }
+            }
+        })
+    };
+
+    let mut roster = Vec::new();
+    let mut requested = false;
+    let mut leave = false;
+    loop {
+        tokio::select! {
+            signal = signals.next() => match signal {
+                Ok(Some(Signal::FloorGranted)) => {
+                    requested = false;
+                    talking.store(true, Ordering::Relaxed);
+                    info!(channel = %name, "You're on air - press Enter to stop talking");
+                }
+                Ok(Some(Signal::FloorTaken(holder))) => {
+                    if std::mem::take(&mut requested) {
+                        info!(channel = %name, "{} has the floor - wait for them to finish", contacts.display_name(holder));
+                    } else {
+                        info!(channel = %name, "{} is talking", contacts.display_name(holder));
+                    }
+                }
+                Ok(Some(Signal::FloorFree)) => {
+                    if talking.swap(false, Ordering::Relaxed) {
+                        info!(channel = %name, "You're off air");
+                    }
+                    info!(channel = %name, "Channel is free - press Enter to talk");
+                }
+                Ok(Some(Signal::Roster(nodes))) => {
+                    print_roster(&name, &nodes, me, &contacts);
+                    roster = nodes;
+                }
+                Ok(Some(Signal::Hangup)) => {
+                    let _ = send_signal(&mut send, &Signal::HangupAck).await;
+                    info!(channel = %name, "The hub closed the channel");
+                    break;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) | Err(_) => {
+                    info!(channel = %name, "Lost the channel hub");
+                    break;
+                }
+            },
+            command = commands.recv() => match command {
+                Some(ChannelCommand::Talk) => {
+                    if talking.swap(false, Ordering::Relaxed) {
+                        send_signal(&mut send, &Signal::FloorRelease).await?;
+                        info!(channel = %name, "You're off air");
+                    } else {
+                        requested = true;
+                        send_signal(&mut send, &Signal::FloorRequest).await?;
+                    }
+                }
+                Some(ChannelCommand::Roster) => print_roster(&name, &roster, me, &contacts),
+                Some(ChannelCommand::Leave) | None => {
+                    leave = true;
+                    break;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => {
+                leave = true;
+                break;
+            }
+        }
+    }
+
+    transmitter.abort();
+    receiver.abort();
+    if leave {
+        hangup_and_wait(&mut send, &mut signals).await?;
+        info!(channel = %name, "Left the channel");
+    }
+    let _ = send.finish();
+    Ok(())
+}
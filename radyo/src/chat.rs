@@ -0,0 +1,162 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use iroh::endpoint::Connection;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn, Instrument};
+
+use crate::call::CallDirection;
+use crate::protocol::{send_signal, Signal, SignalReader};
+
+// Longest chat message we send, well inside a signal line
+const MAX_CHAT_LEN: usize = 4 * 1024;
+
+/// A text message sent during a call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub id: u64,
+    /// We wrote it, rather than the other side
+    pub from_us: bool,
+    pub sent_at: SystemTime,
+    pub text: String,
+    /// The other side confirmed it arrived - always true for messages we received
+    pub delivered: bool,
+}
+
+impl ChatMessage {
+    /// When the message was written, in local time.
+    pub fn time(&self) -> String {
+        DateTime::<Local>::from(self.sent_at).format("%H:%M:%S").to_string()
+    }
+}
+
+/// Every chat message of one call, kept with the call history.
+#[derive(Debug, Clone, Default)]
+pub struct ChatLog {
+    messages: Arc<Mutex<Vec<ChatMessage>>>,
+}
+
+impl ChatLog {
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    fn push(&self, message: ChatMessage) {
+        self.messages.lock().unwrap().push(message);
+    }
+
+    // Mark our message `id` as delivered and return its text
+    fn mark_delivered(&self, id: u64) -> Option<String> {
+        let mut messages = self.messages.lock().unwrap();
+        let message = messages.iter_mut().find(|m| m.from_us && m.id == id)?;
+        message.delivered = true;
+        Some(message.text.clone())
+    }
+}
+
+/// Text chat alongside a call, on its own stream of the call's connection.
+///
+/// The dialer opens the stream once the call is answered and the called side
+/// accepts it. Messages typed before the stream is up are sent as soon as it is.
+pub struct ChatLink {
+    outgoing: mpsc::UnboundedSender<String>,
+    task: JoinHandle<()>,
+}
+
+impl ChatLink {
+    pub fn start(conn: Connection, direction: CallDirection, log: ChatLog, remote_name: String) -> Self {
+        let (outgoing, rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            if let Err(e) = run_chat(conn, direction, log, rx, &remote_name).await {
+                warn!("Chat closed: {}", e);
+            }
+        }.in_current_span());
+        Self { outgoing, task }
+    }
+
+    pub fn send(&self, text: String) {
+        let _ = self.outgoing.send(text);
+    }
+
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for ChatLink {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn run_chat(
+    conn: Connection,
+    direction: CallDirection,
+    log: ChatLog,
+    mut outgoing: mpsc::UnboundedReceiver<String>,
+    remote_name: &str,
+) -> Result<()> {
+    let (mut send, mut signals) = match direction {
+        CallDirection::Outgoing => {
+            let (mut send, recv) = conn.open_bi().await?;
+            send_signal(&mut send, &Signal::ChatOpen).await?;
+            (send, SignalReader::new(recv))
+        }
+        CallDirection::Incoming => {
+            let (send, recv) = conn.accept_bi().await?;
+            let mut signals = SignalReader::new(recv);
+            if signals.next().await? != Some(Signal::ChatOpen) {
+                anyhow::bail!("the other side didn't open a chat");
+            }
+            (send, signals)
+        }
+    };
+
+    let mut next_id = 0;
+    loop {
+        tokio::select! {
+            text = outgoing.recv() => {
+                let Some(text) = text else { break };
+                next_id += 1;
+                let message = ChatMessage {
+                    id: next_id,
+                    from_us: true,
+                    sent_at: SystemTime::now(),
+                    text: clip(&text.replace('\n', " "), MAX_CHAT_LEN),
+                    delivered: false,
+                };
+                info!("{} you: {}", message.time(), message.text);
+                send_signal(&mut send, &Signal::Chat { id: message.id, sent_at: message.sent_at, text: message.text.clone() }).await?;
+                log.push(message);
+            }
+            signal = signals.next() => match signal? {
+                Some(Signal::Chat { id, sent_at, text }) => {
+                    let message = ChatMessage { id, from_us: false, sent_at, text, delivered: true };
+                    info!("{} {}: {}", message.time(), remote_name, message.text);
+                    log.push(message);
+                    send_signal(&mut send, &Signal::ChatReceipt(id)).await?;
+                }
+                Some(Signal::ChatReceipt(id)) => {
+                    if let Some(text) = log.mark_delivered(id) {
+                        info!("Delivered: {}", text);
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            },
+        }
+    }
+    let _ = send.finish();
+    Ok(())
+}
+
+// The first `max` bytes of `text`, without splitting a character
+fn clip(text: &str, max: usize) -> String {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
+}
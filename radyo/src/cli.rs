@@ -1,18 +1,413 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use std::io::BufRead;
+use std::path::PathBuf;
+use anyhow::Result;
+use network::NetworkArgs;
+use tracing::warn;
+use crate::call::{send_command, CallCommand, CallManager};
+use crate::channel::ChannelCommand;
+use crate::conference::ConferenceCommand;
+use crate::contacts::ContactBook;
+use crate::dtmf::is_dtmf_digit;
+use crate::recording::RecordingMode;
 
 #[derive(Subcommand)]
 pub enum Cmd {
     Caller { 
-        #[arg(default_value = "lost_woods")]
-        ringtone: String 
+        /// Ringtone to play for incoming calls (default: from radyo.toml, else lost_woods)
+        ringtone: Option<String>,
+        /// Let a second caller wait with a beep instead of getting BUSY
+        #[arg(long)]
+        call_waiting: bool,
+        #[command(flatten)]
+        call: CallArgs,
+        #[command(flatten)]
+        forwarding: ForwardArgs,
     },
     Peer { 
+        /// Node ticket or contact name to call
         token: String,
+        #[command(flatten)]
+        call: CallArgs,
     },
+    /// Host a conference call that others dial into
+    Conference,
+    /// Push-to-talk walkie-talkie channels
+    Channel {
+        #[command(subcommand)]
+        action: ChannelCmd,
+    },
+    /// Run an internet radio station that streams a playlist to listeners
+    Station {
+        /// Tracks to play in order, as ringtone names or file paths (default: every file in ringtons/)
+        tracks: Vec<String>,
+    },
+    /// Listen to a radio station
+    Tune {
+        /// Station ticket or contact name
+        ticket: String,
+    },
+    /// Answer calls with a greeting and record messages
+    Voicemail {
+        /// Greeting to play, as a ringtone name or audio file
+        #[arg(long)]
+        greeting: Option<String>,
+        /// Contact to put callers through to when they press 0
+        #[arg(long, value_name = "CONTACT")]
+        operator: Option<String>,
+        /// Longest message to record, in seconds
+        #[arg(long, value_name = "SECS", default_value_t = 60)]
+        max_length: u64,
+    },
+    /// Run the phone service with a full-screen terminal UI
+    Tui {
+        /// Ringtone to play for incoming calls (default: from radyo.toml, else lost_woods)
+        ringtone: Option<String>,
+        /// Let a second caller wait with a beep instead of getting BUSY
+        #[arg(long)]
+        call_waiting: bool,
+        #[command(flatten)]
+        call: CallArgs,
+        #[command(flatten)]
+        forwarding: ForwardArgs,
+    },
+    /// Run the phone service in the background, controlled over a local socket
+    Daemon {
+        /// Ringtone to play for incoming calls (default: from radyo.toml, else lost_woods)
+        ringtone: Option<String>,
+        /// Let a second caller wait with a beep instead of getting BUSY
+        #[arg(long)]
+        call_waiting: bool,
+        /// Control socket path (default: radyo.sock in the runtime directory)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        #[command(flatten)]
+        call: CallArgs,
+        #[command(flatten)]
+        forwarding: ForwardArgs,
+    },
+    /// Control a running daemon
+    Ctl {
+        /// Control socket path (default: radyo.sock in the runtime directory)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        action: CtlCmd,
+    },
+    /// Manage the contacts you can call by name
+    Contacts {
+        #[command(subcommand)]
+        action: ContactsCmd,
+    },
+    /// Show or change the settings in radyo.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigCmd,
+    },
+    /// Check whether a contact is available, busy or on do-not-disturb, without ringing them
+    Status { contact: String },
+}
+
+/// In-call options shared by the phone service and peer mode.
+#[derive(Args, Debug, Clone)]
+pub struct CallArgs {
+    /// Periodically print RTT, path type, traffic and packet loss
+    #[arg(long)]
+    pub stats: bool,
+    /// Ringtone file to loop while the other side has you on hold (default: taken_it_slow)
+    #[arg(long)]
+    pub hold_music: Option<String>,
+    /// Refuse when the other side asks to record a call
+    #[arg(long)]
+    pub no_recording: bool,
+    /// Also play keypad digits to the other side as in-band tones
+    #[arg(long)]
+    pub dtmf_tones: bool,
+    /// Playback volume calls start at, in percent (default: 100)
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=200))]
+    pub volume: Option<u8>,
+    /// Seconds an outgoing call rings before giving up (default: 30)
+    #[arg(long, value_name = "SECS")]
+    pub dial_timeout: Option<u64>,
+    /// Seconds each attempt to reach the other side may take (default: 15)
+    #[arg(long, value_name = "SECS")]
+    pub connect_timeout: Option<u64>,
+    /// Extra attempts when the other side can't be reached, waiting longer each time (default: 2)
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+    /// Secret key file that keeps your node ID the same across runs
+    #[arg(long, value_name = "PATH")]
+    pub identity: Option<PathBuf>,
+}
+
+/// Call forwarding rules for the phone service. Each takes a contact name or node ticket.
+#[derive(Args, Debug, Clone)]
+pub struct ForwardArgs {
+    /// Forward every call without ringing
+    #[arg(long, value_name = "CONTACT")]
+    pub forward_always: Option<String>,
+    /// Forward callers who would otherwise get BUSY
+    #[arg(long, value_name = "CONTACT")]
+    pub forward_busy: Option<String>,
+    /// Forward calls nobody answers within --no-answer-after seconds
+    #[arg(long, value_name = "CONTACT")]
+    pub forward_no_answer: Option<String>,
+    /// Seconds to ring before --forward-no-answer kicks in (default: 20)
+    #[arg(long, value_name = "SECS")]
+    pub no_answer_after: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum ChannelCmd {
+    /// Start a channel on this node and print its ticket
+    Create { name: String },
+    /// Join a channel with its ticket
+    Join { ticket: String },
+}
+
+#[derive(Subcommand)]
+pub enum ContactsCmd {
+    /// Save a node ticket under a name
+    Add {
+        name: String,
+        ticket: String,
+        /// Put the contact in a group, e.g. family
+        #[arg(long)]
+        group: Option<String>,
+    },
+    /// Show saved contacts
+    List {
+        /// Also check who is available, busy, on do-not-disturb or offline
+        #[arg(long)]
+        presence: bool,
+    },
+    /// Forget a contact
+    Remove { name: String },
+    /// Put a contact in a group, or leave out the group to take them out of theirs
+    Group { name: String, group: Option<String> },
+    /// Ring with this track when a contact calls (leave it out for the default)
+    Ringtone {
+        /// Contact name, or a group name with --group
+        name: String,
+        ringtone: Option<String>,
+        /// Set the ringtone for everyone in the group
+        #[arg(long)]
+        group: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCmd {
+    /// Print every setting, including defaults, and where the file lives
+    Show,
+    /// Change one setting, e.g. `timeouts.dial 45` or `audio.output_device "USB Headset"`
+    Set { key: String, value: String },
+}
+
+/// Requests to the daemon. Each prints the daemon's JSON reply.
+#[derive(Subcommand)]
+pub enum CtlCmd {
+    /// Call a contact or node ticket
+    Dial { target: String },
+    /// Answer a ringing call (default: the oldest one)
+    Answer { call_id: Option<u128> },
+    /// Turn a ringing call away with BUSY (default: the oldest one)
+    Decline { call_id: Option<u128> },
+    /// Hang up a call (default: the focused one)
+    Hangup { call_id: Option<u128> },
+    /// Show the daemon's ticket and calls
+    Status,
+    /// Turn do-not-disturb on or off (default: flip it)
+    Dnd {
+        #[arg(value_parser = ["on", "off"])]
+        state: Option<String>,
+    },
+    /// Print call events as they happen, one JSON object per line
+    Events,
 }
 
 #[derive(Parser)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Cmd,
+    #[command(flatten)]
+    pub log: LogArgs,
+    #[command(flatten)]
+    pub network: NetworkArgs,
+}
+
+/// How much radyo logs, and in which format. Logs go to stderr.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct LogArgs {
+    /// Log more: -v for debug, -vv for trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Log less: -q for warnings and errors only, -qq for errors only
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "verbose")]
+    pub quiet: u8,
+    /// Log one JSON object per line, e.g. to ship logs elsewhere
+    #[arg(long, global = true)]
+    pub log_json: bool,
+}
+
+pub const CALL_KEYS_HELP: &str =
+    "⌨️  Call keys (then Enter): a=answer n=decline h=hold r=resume m=mute/unmute +/-=volume v <0-200>=set volume s=switch calls t <contact>=transfer at <contact>=attended transfer x=complete transfer c <text>=chat f <path>=send a file g=download the offered file rec [tracks]=record/stop d <digits>=keypad q=hang up dnd [on|off]=do not disturb";
+
+pub fn parse_call_key(line: &str) -> Result<CallCommand> {
+    let line = line.trim();
+    let (key, arg) = line.split_once(' ').map(|(k, a)| (k, a.trim())).unwrap_or((line, ""));
+    match (key, arg) {
+        ("a" | "answer", "") => Ok(CallCommand::Answer),
+        ("n" | "decline", "") => Ok(CallCommand::Decline),
+        ("h" | "hold", "") => Ok(CallCommand::Hold),
+        ("r" | "resume", "") => Ok(CallCommand::Resume),
+        ("q" | "hangup", "") => Ok(CallCommand::Hangup),
+        ("m" | "mute", "") => Ok(CallCommand::ToggleMute),
+        ("s" | "switch", "") => Ok(CallCommand::Switch),
+        ("+", "") => Ok(CallCommand::VolumeUp),
+        ("-", "") => Ok(CallCommand::VolumeDown),
+        ("v" | "volume", percent) => percent
+            .parse()
+            .map(CallCommand::SetVolume)
+            .map_err(|_| anyhow::anyhow!("Volume must be a number from 0 to 200")),
+        ("t" | "transfer", target) if !target.is_empty() => {
+            Ok(CallCommand::Transfer(ContactBook::load()?.resolve(target)?))
+        }
+        ("at" | "attended", target) if !target.is_empty() => {
+            Ok(CallCommand::ConsultTransfer(ContactBook::load()?.resolve(target)?))
+        }
+        ("x" | "complete", "") => Ok(CallCommand::CompleteTransfer),
+        ("c" | "chat", text) if !text.is_empty() => Ok(CallCommand::Chat(text.to_string())),
+        ("f" | "file", path) if !path.is_empty() => Ok(CallCommand::SendFile(path.into())),
+        ("g" | "get", "") => Ok(CallCommand::AcceptFile),
+        ("rec" | "record", "") => Ok(CallCommand::ToggleRecording(RecordingMode::Mixed)),
+        ("d" | "dtmf", digits) if !digits.is_empty() => {
+            if !digits.chars().all(is_dtmf_digit) {
+                anyhow::bail!("Keypad digits are 0-9, *, # and A-D");
+            }
+            Ok(CallCommand::Dtmf(digits.to_ascii_uppercase()))
+        }
+        ("rec" | "record", "tracks") => Ok(CallCommand::ToggleRecording(RecordingMode::SeparateTracks)),
+        _ => Err(anyhow::anyhow!("Unknown call key '{}'\n{}", line, CALL_KEYS_HELP)),
+    }
+}
+
+// `dnd` flips do-not-disturb, `dnd on` and `dnd off` set it
+fn parse_dnd_key(arg: &str) -> Result<bool> {
+    let enabled = match arg {
+        "" => return Ok(CallManager::toggle_do_not_disturb()),
+        "on" => true,
+        "off" => false,
+        _ => anyhow::bail!("Use dnd, dnd on or dnd off"),
+    };
+    CallManager::set_do_not_disturb(enabled);
+    Ok(enabled)
+}
+
+fn print_dnd(enabled: bool) {
+    if enabled {
+        println!("🔕 Do not disturb is on");
+    } else if CallManager::dnd_active() {
+        println!("🔕 Do not disturb is off, but it's quiet hours");
+    } else {
+        println!("🔔 Do not disturb is off");
+    }
+}
+
+pub const CONFERENCE_KEYS_HELP: &str =
+    "⌨️  Conference keys (then Enter): add <contact>=call someone in kick <name>=remove someone l=list m=mute/unmute q=end the conference";
+
+pub fn parse_conference_key(line: &str) -> Result<ConferenceCommand> {
+    let line = line.trim();
+    let (key, arg) = line.split_once(' ').map(|(k, a)| (k, a.trim())).unwrap_or((line, ""));
+    match (key, arg) {
+        ("add", target) if !target.is_empty() => {
+            Ok(ConferenceCommand::Add(ContactBook::load()?.resolve(target)?))
+        }
+        ("kick", name) if !name.is_empty() => Ok(ConferenceCommand::Remove(name.to_string())),
+        ("l" | "list", "") => Ok(ConferenceCommand::Roster),
+        ("m" | "mute", "") => Ok(ConferenceCommand::ToggleMute),
+        ("q" | "end", "") => Ok(ConferenceCommand::End),
+        _ => Err(anyhow::anyhow!("Unknown conference key '{}'\n{}", line, CONFERENCE_KEYS_HELP)),
+    }
+}
+
+// Read conference keys from stdin for the host
+pub fn spawn_conference_reader() -> tokio::sync::mpsc::UnboundedReceiver<ConferenceCommand> {
+    println!("{}", CONFERENCE_KEYS_HELP);
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_conference_key(&line) {
+                Ok(command) => {
+                    if tx.send(command).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
+    rx
+}
+
+pub const CHANNEL_KEYS_HELP: &str =
+    "⌨️  Channel keys: Enter=talk/stop talking l=list members q=leave";
+
+// Read channel keys from stdin - a bare Enter is the push-to-talk button
+pub fn spawn_channel_reader() -> tokio::sync::mpsc::UnboundedReceiver<ChannelCommand> {
+    println!("{}", CHANNEL_KEYS_HELP);
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let command = match line.trim() {
+                "" | "t" | "talk" => ChannelCommand::Talk,
+                "l" | "list" => ChannelCommand::Roster,
+                "q" | "leave" => ChannelCommand::Leave,
+                _ => {
+                    println!("{}", CHANNEL_KEYS_HELP);
+                    continue;
+                }
+            };
+            if tx.send(command).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+// Read call keys from stdin and forward them to the active call.
+// Uses a plain thread so a pending read never blocks runtime shutdown.
+pub fn spawn_command_reader() {
+    println!("{}", CALL_KEYS_HELP);
+    std::thread::spawn(|| {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Do-not-disturb belongs to the phone, not to a call
+            if let Some(arg) = line.trim().strip_prefix("dnd") {
+                match parse_dnd_key(arg.trim()) {
+                    Ok(enabled) => print_dnd(enabled),
+                    Err(e) => warn!("{}", e),
+                }
+                continue;
+            }
+            match parse_call_key(&line) {
+                Ok(command) => {
+                    if !send_command(command.clone()) {
+                        warn!("No active call to {}", command);
+                    }
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
 }
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use iroh::protocol::Router;
+use iroh::{Endpoint, NodeAddr};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::call::{dial, send_command_to, subscribe_events, CallCommand, CallEvent, CallOutcome, CallPhase};
+use crate::config::Config;
+use crate::files::blobs_protocol;
+
+/// Places radyo calls from other programs.
+///
+/// Owns an endpoint that also serves the files offered during its calls.
+/// Calls run in the background; each [`dial`](Self::dial) hands back a
+/// [`CallHandle`] to control it:
+///
+/// ```text
+/// let client = RadyoClient::new().await?;
+/// let call = client.dial(ticket).await?;
+/// call.mute();
+/// let outcome = call.await?;
+/// client.shutdown().await?;
+/// ```
+pub struct RadyoClient {
+    router: Router,
+}
+
+impl RadyoClient {
+    /// Places calls from an endpoint with radyo.toml's relay and discovery
+    /// settings.
+    pub async fn new() -> Result<Self> {
+        let endpoint = Config::load()?.endpoint_builder()?.bind().await?;
+        Ok(Self::from_endpoint(endpoint))
+    }
+
+    /// Places calls from an endpoint you configured yourself.
+    pub fn from_endpoint(endpoint: Endpoint) -> Self {
+        let router = Router::builder(endpoint.clone())
+            .accept(iroh_blobs::ALPN, blobs_protocol(&endpoint))
+            .spawn();
+        Self { router }
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        self.router.endpoint()
+    }
+
+    /// Connects to `node_addr` and rings it.
+    ///
+    /// Fails if the other side can't be reached; once it returns, the other
+    /// side is ringing.
+    pub async fn dial(&self, node_addr: impl Into<NodeAddr>) -> Result<CallHandle> {
+        let call = dial(self.endpoint(), node_addr.into()).await?;
+        let call_id = call.call_id();
+        let task = tokio::spawn(call.run());
+        Ok(CallHandle { call_id, task })
+    }
+
+    pub async fn shutdown(self) -> Result<()> {
+        self.router.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// Controls a call placed with [`RadyoClient::dial`].
+///
+/// Await the handle for the call's [`CallOutcome`]. Dropping it leaves the
+/// call running.
+pub struct CallHandle {
+    call_id: u128,
+    task: JoinHandle<Result<CallOutcome>>,
+}
+
+impl CallHandle {
+    pub fn call_id(&self) -> u128 {
+        self.call_id
+    }
+
+    /// Hangs up - returns false if the call is already over.
+    pub fn hangup(&self) -> bool {
+        self.send(CallCommand::Hangup)
+    }
+
+    pub fn mute(&self) -> bool {
+        self.send(CallCommand::Mute)
+    }
+
+    pub fn unmute(&self) -> bool {
+        self.send(CallCommand::Unmute)
+    }
+
+    /// Sends any [`CallCommand`] to this call, whether or not it has focus.
+    pub fn send(&self, command: CallCommand) -> bool {
+        !self.task.is_finished() && send_command_to(self.call_id, command)
+    }
+
+    /// This call's events from now on, ending once the call does.
+    pub fn events(&self) -> CallEvents {
+        CallEvents { call_id: self.call_id, events: subscribe_events(), ended: self.task.is_finished() }
+    }
+}
+
+impl Future for CallHandle {
+    type Output = Result<CallOutcome>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.task).poll(cx).map(|result| result?)
+    }
+}
+
+/// The events of one call, from [`CallHandle::events`].
+pub struct CallEvents {
+    call_id: u128,
+    events: broadcast::Receiver<(u128, CallEvent)>,
+    ended: bool,
+}
+
+impl CallEvents {
+    /// The next event, or None once the call has ended.
+    pub async fn next(&mut self) -> Option<CallEvent> {
+        while !self.ended {
+            match self.events.recv().await {
+                Ok((call_id, event)) if call_id == self.call_id => {
+                    self.ended = event == CallEvent::PhaseChanged(CallPhase::Ended);
+                    return Some(event);
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::Result;
+use iroh::endpoint::{Connection, SendStream};
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use ringbuf::HeapProd;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::call::{close_and_wait, new_call_id, CallManager};
+use crate::connect::connect;
+use crate::contacts::ContactBook;
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+use crate::roster::{run_member, Roster};
+use crate::voice::{decode_frame, encode_frame, queue_playback, LocalAudio, VoiceControls, FRAME_SAMPLES};
+
+// Most participants one host mixes, not counting the host
+pub const MAX_PARTICIPANTS: usize = 8;
+
+// One frame from everyone is mixed every 20 ms, the length of a voice frame
+const MIX_INTERVAL: Duration = Duration::from_millis(20);
+
+// Frames buffered per participant before the oldest are dropped
+const MAX_QUEUED_FRAMES: usize = 10;
+
+/// What the host can do to a conference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConferenceCommand {
+    /// Call someone and add them once they answer
+    Add(NodeTicket),
+    /// Hang up on a participant, by contact name or node ID prefix
+    Remove(String),
+    Roster,
+    ToggleMute,
+    End,
+}
+
+/// The host side of a conference call.
+///
+/// Participants dial the host like any other radyo node. The host mixes one
+/// frame from every participant and its own microphone, then sends each
+/// participant the mix without their own voice.
+#[derive(Debug, Clone)]
+pub struct Conference {
+    conference_id: u128,
+    endpoint: Endpoint,
+    participants: Roster<ParticipantVoice>,
+    // Loaded once when the conference starts, to name participants in the log
+    contacts: Arc<ContactBook>,
+    controls: VoiceControls,
+}
+
+// What the mixer keeps for each participant
+#[derive(Debug, Default)]
+struct ParticipantVoice {
+    frames: VecDeque<Vec<i16>>,
+    seq: u32,
+}
+
+impl ProtocolHandler for Conference {
+    async fn accept(&self, conn: Connection) -> Result<(), AcceptError> {
+        let conference = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = conference.accept_participant(conn).await {
+                warn!(conference_id = conference.conference_id, "Couldn't add participant: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Conference {
+    pub fn new(endpoint: Endpoint) -> Self {
+        Self {
+            conference_id: new_call_id(),
+            participants: Roster::with_limit(endpoint.node_id(), MAX_PARTICIPANTS),
+            contacts: Arc::new(ContactBook::load_or_default()),
+            endpoint,
+            controls: VoiceControls::new(),
+        }
+    }
+
+    pub fn conference_id(&self) -> u128 {
+        self.conference_id
+    }
+
+    /// Everyone in the conference, host first.
+    pub fn roster(&self) -> Vec<NodeId> {
+        self.participants.node_ids()
+    }
+
+    pub fn print_roster(&self) {
+        let names: Vec<String> = self.roster()
+            .into_iter()
+            .skip(1)
+            .map(|node_id| self.contacts.display_name(node_id))
+            .collect();
+        info!(conference_id = self.conference_id, "Roster: you (host){}{}",
+            if names.is_empty() { "" } else { ", " }, names.join(", "));
+    }
+
+    // Someone dialed the host: answer straight away and join them
+    async fn accept_participant(&self, conn: Connection) -> Result<()> {
+        let (mut send, recv) = conn.accept_bi().await?;
+        let mut signals = SignalReader::new(recv);
+        if signals.next().await? != Some(Signal::IncomingCall) {
+            return Ok(());
+        }
+        // Taking the seat before answering means two callers can't both get the last one
+        let node_id = conn.remote_node_id()?;
+        let Some(outgoing) = self.participants.add(node_id, conn.clone(), ParticipantVoice::default()) else {
+            info!(conference_id = self.conference_id, "Conference is full - rejecting call");
+            send_signal(&mut send, &Signal::Busy).await?;
+            close_and_wait(&mut send).await;
+            return Ok(());
+        };
+        if let Err(e) = send_signal(&mut send, &Signal::Answer).await {
+            self.participants.remove(node_id);
+            return Err(e);
+        }
+        self.join(node_id, conn, send, signals, outgoing);
+        Ok(())
+    }
+
+    /// Calls `ticket` and adds them once they answer.
+    pub fn invite(&self, ticket: NodeTicket) {
+        let conference = self.clone();
+        tokio::spawn(async move {
+            let name = conference.contacts.display_name(ticket.node_addr().node_id);
+            if let Err(e) = conference.ring(ticket).await {
+                warn!(conference_id = conference.conference_id, "Couldn't add {}: {}", name, e);
+            }
+        });
+    }
+
+    async fn ring(&self, ticket: NodeTicket) -> Result<()> {
+        // Saves ringing someone we couldn't take anyway
+        if self.participants.is_full() {
+            anyhow::bail!("the conference is full");
+        }
+        let name = self.contacts.display_name(ticket.node_addr().node_id);
+        let conn = connect(&self.endpoint, ticket.node_addr().clone()).await?;
+        let (mut send, recv) = conn.open_bi().await?;
+        let mut signals = SignalReader::new(recv);
+        send_signal(&mut send, &Signal::IncomingCall).await?;
+        info!(conference_id = self.conference_id, "Ringing {}...", name);
+
+        let wait_for_answer = async {
+            loop {
+                match signals.next().await? {
+                    Some(Signal::Answer) => return Ok(true),
+                    Some(Signal::Busy | Signal::DoNotDisturb | Signal::Redirect(_)) | None => return Ok(false),
+                    Some(_) => {}
+                }
+            }
+        };
+        match tokio::time::timeout(CallManager::timeouts().dial(), wait_for_answer).await {
+            Ok(Ok(true)) => {
+                // Someone may have taken the last seat while they were ringing
+                let node_id = conn.remote_node_id()?;
+                let Some(outgoing) = self.participants.add(node_id, conn.clone(), ParticipantVoice::default()) else {
+                    hangup_and_wait(&mut send, &mut signals).await?;
+                    anyhow::bail!("the conference filled up while they were ringing");
+                };
+                self.join(node_id, conn, send, signals, outgoing);
+                Ok(())
+            }
+            Ok(Err(e)) => Err(e),
+            _ => {
+                info!(conference_id = self.conference_id, "{} didn't pick up", name);
+                let _ = send_signal(&mut send, &Signal::Hangup).await;
+                let _ = send.finish();
+                Ok(())
+            }
+        }
+    }
+
+    // The participant is on the line and has a seat: start taking their audio and tell everyone
+    fn join(
+        &self,
+        node_id: NodeId,
+        conn: Connection,
+        send: SendStream,
+        signals: SignalReader,
+        outgoing: mpsc::UnboundedReceiver<Signal>,
+    ) {
+        info!(conference_id = self.conference_id, "{} joined the conference", self.contacts.display_name(node_id));
+
+        tokio::spawn(self.clone().receive_voice(node_id, conn));
+        tokio::spawn(self.clone().run_participant(node_id, send, signals, outgoing));
+        self.broadcast_roster();
+    }
+
+    async fn receive_voice(self, node_id: NodeId, conn: Connection) {
+        while let Ok(datagram) = conn.read_datagram().await {
+            let Some((_seq, samples)) = decode_frame(&datagram) else {
+                continue;
+            };
+            let mut participants = self.participants.lock();
+            let Some(participant) = participants.iter_mut().find(|p| p.node_id == node_id) else {
+                break;
+            };
+            let frames = &mut participant.state.frames;
+            frames.push_back(samples);
+            while frames.len() > MAX_QUEUED_FRAMES {
+                frames.pop_front();
+            }
+        }
+    }
+
+    // Carries one participant's control stream until either side hangs up
+    async fn run_participant(
+        self,
+        node_id: NodeId,
+        send: SendStream,
+        signals: SignalReader,
+        outgoing: mpsc::UnboundedReceiver<Signal>,
+    ) {
+        let name = self.contacts.display_name(node_id);
+        run_member(send, signals, outgoing, |signal| match signal {
+            Signal::Muted => info!(conference_id = self.conference_id, "{} is muted", name),
+            Signal::Unmuted => info!(conference_id = self.conference_id, "{} unmuted", name),
+            _ => {}
+        })
+        .await;
+        self.participants.remove(node_id);
+        info!(conference_id = self.conference_id, "{} left the conference", name);
+        self.broadcast_roster();
+    }
+
+    fn broadcast_roster(&self) {
+        self.participants.broadcast(Signal::Roster(self.roster()), None);
+        self.print_roster();
+    }
+
+    /// Hangs up on the participant whose contact name or node ID starts with `name`.
+    pub fn remove(&self, name: &str) -> bool {
+        let found = self.participants.lock().iter().map(|p| p.node_id).find(|node_id| {
+            self.contacts.display_name(*node_id) == name || node_id.to_string().starts_with(name)
+        });
+        found.is_some_and(|node_id| self.participants.send_to(node_id, Signal::Hangup))
+    }
+
+    pub fn toggle_mute(&self) {
+        let muted = !self.controls.is_muted();
+        self.controls.set_muted(muted);
+        if muted {
+            info!(conference_id = self.conference_id, "Microphone muted");
+        } else {
+            info!(conference_id = self.conference_id, "Microphone unmuted");
+        }
+    }
+
+    /// Hangs up on everyone and waits for them to leave.
+    pub async fn end(&self) {
+        self.participants.hang_up_all().await;
+    }
+
+    /// Starts the host's microphone and speaker and the mixer feeding everyone.
+    pub fn start_mixer(&self) -> (LocalAudio, tokio::task::JoinHandle<()>) {
+        let (audio, mic, speaker) = LocalAudio::start(self.controls.clone(), self.conference_id);
+        let mixer = tokio::spawn(self.clone().mix(mic, speaker));
+        (audio, mixer)
+    }
+
+    async fn mix(self, mut mic: mpsc::Receiver<Vec<i16>>, mut speaker: HeapProd<f32>) {
+        let mut tick = tokio::time::interval(MIX_INTERVAL);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            tick.tick().await;
+            // Keep the host's microphone close to real time
+            let mut host = mic.try_recv().ok();
+            while mic.len() > 2 {
+                host = mic.try_recv().ok();
+            }
+            if self.controls.is_muted() {
+                host = None;
+            }
+
+            let mut participants = self.participants.lock();
+            let frames: Vec<Option<Vec<i16>>> = participants.iter_mut().map(|p| p.state.frames.pop_front()).collect();
+            if host.is_none() && frames.iter().all(Option::is_none) {
+                continue;
+            }
+            let mut total = vec![0i32; FRAME_SAMPLES];
+            for frame in frames.iter().chain(std::iter::once(&host)).flatten() {
+                add_frame(&mut total, frame);
+            }
+
+            // Everyone hears everyone but themselves
+            for (participant, own) in participants.iter_mut().zip(&frames) {
+                let mix = mix_without(&total, own.as_deref());
+                let voice = &mut participant.state;
+                let _ = participant.conn.send_datagram(encode_frame(voice.seq, &mix));
+                voice.seq = voice.seq.wrapping_add(1);
+            }
+            queue_playback(&mut speaker, &mix_without(&total, host.as_deref()));
+        }
+    }
+}
+
+fn add_frame(total: &mut [i32], frame: &[i16]) {
+    for (sum, sample) in total.iter_mut().zip(frame) {
+        *sum += *sample as i32;
+    }
+}
+
+fn mix_without(total: &[i32], own: Option<&[i16]>) -> Vec<i16> {
+    total
+        .iter()
+        .enumerate()
+        .map(|(i, sum)| {
+            let own = own.and_then(|frame| frame.get(i)).copied().unwrap_or(0) as i32;
+            (sum - own).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        })
+        .collect()
+}
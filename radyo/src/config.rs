@@ -0,0 +1,471 @@
+use anyhow::{Context, Result};
+use iroh::endpoint::{Builder, RelayMode};
+use iroh::{RelayUrl, SecretKey};
+use network::{endpoint_builder, NetworkArgs};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::info;
+
+use crate::contacts::config_dir;
+use crate::dnd::QuietHours;
+use crate::forwarding::DEFAULT_NO_ANSWER_TIMEOUT;
+
+// How long an outgoing call rings before giving up
+pub const DIAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How long an incoming call waits for the ringtone to start playing
+pub const AUDIO_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long one attempt to reach a node may take
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// radyo's settings, stored as `radyo.toml` in the radyo config dir.
+///
+/// Every field has a default, so the file only needs the settings you want
+/// to change. Command-line flags override it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Secret key file, so the node ID (and ticket) survives restarts.
+    /// Relative paths are relative to the config dir. None means a new
+    /// node ID every run.
+    pub identity: Option<PathBuf>,
+    pub ringtone: String,
+    pub hold_music: String,
+    /// Only reach nodes on the local network, found with mDNS. Ignores
+    /// `relay` and `discovery`.
+    pub local: bool,
+    /// How to find a node from its node ID
+    pub discovery: Vec<DiscoveryService>,
+    pub timeouts: Timeouts,
+    pub retry: RetrySettings,
+    pub audio: AudioSettings,
+    pub relay: RelaySettings,
+    pub dnd: DndSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            identity: None,
+            ringtone: "lost_woods".to_string(),
+            hold_music: "taken_it_slow".to_string(),
+            local: false,
+            discovery: vec![DiscoveryService::N0],
+            timeouts: Timeouts::default(),
+            retry: RetrySettings::default(),
+            audio: AudioSettings::default(),
+            relay: RelaySettings::default(),
+            dnd: DndSettings::default(),
+        }
+    }
+}
+
+/// Call timeouts, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// How long one attempt to reach a node may take
+    pub connect: u64,
+    /// How long an outgoing call rings before giving up
+    pub dial: u64,
+    /// How long an incoming call waits for the ringtone to start
+    pub audio_ready: u64,
+    /// How long a call rings before it is forwarded on no answer
+    pub no_answer: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: CONNECT_TIMEOUT.as_secs(),
+            dial: DIAL_TIMEOUT.as_secs(),
+            audio_ready: AUDIO_READY_TIMEOUT.as_secs(),
+            no_answer: DEFAULT_NO_ANSWER_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn connect(&self) -> Duration {
+        Duration::from_secs(self.connect)
+    }
+
+    pub fn dial(&self) -> Duration {
+        Duration::from_secs(self.dial)
+    }
+
+    pub fn audio_ready(&self) -> Duration {
+        Duration::from_secs(self.audio_ready)
+    }
+
+    pub fn no_answer(&self) -> Duration {
+        Duration::from_secs(self.no_answer)
+    }
+}
+
+/// How hard to try reaching a node that can't be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrySettings {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Wait before the first retry, in milliseconds - it doubles each time
+    pub backoff_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self { retries: 2, backoff_ms: 1000 }
+    }
+}
+
+impl RetrySettings {
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff_ms)
+    }
+}
+
+/// Sound settings for calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioSettings {
+    /// Playback volume calls start at, in percent (0-200)
+    pub volume: u8,
+    /// Microphone to use, by name (default: the system's default input)
+    pub input_device: Option<String>,
+    /// Speaker to use, by name (default: the system's default output)
+    pub output_device: Option<String>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self { volume: 100, input_device: None, output_device: None }
+    }
+}
+
+/// Which relay servers the endpoint uses when a direct path isn't possible.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelaySettings {
+    pub mode: RelayChoice,
+    /// Relay servers for `mode = "custom"`
+    pub urls: Vec<String>,
+}
+
+pub use network::{DiscoveryService, RelayChoice};
+
+impl RelaySettings {
+    pub fn relay_mode(&self) -> Result<RelayMode> {
+        if self.mode != RelayChoice::Custom {
+            return self.mode.relay_mode(&[]);
+        }
+        if self.urls.is_empty() {
+            anyhow::bail!("relay mode \"custom\" needs at least one relay - give --relay or set relay.urls");
+        }
+        let urls = self
+            .urls
+            .iter()
+            .map(|url| url.parse::<RelayUrl>().with_context(|| format!("Invalid relay URL {}", url)))
+            .collect::<Result<Vec<_>>>()?;
+        self.mode.relay_mode(&urls)
+    }
+}
+
+/// Do-not-disturb: turn incoming calls away instead of ringing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DndSettings {
+    /// On all the time, not just during `schedule` (toggle with `dnd`)
+    pub enabled: bool,
+    /// Daily quiet hours, e.g. `{ from = "22:00", to = "07:00" }`
+    pub schedule: Vec<QuietHours>,
+    /// Contact or ticket that takes calls silently instead of declining them
+    pub voicemail: Option<String>,
+    /// Contacts who ring through anyway
+    pub favourites: Vec<String>,
+}
+
+// Network flags from the command line, laid over radyo.toml by Config::load
+static NETWORK_ARGS: OnceLock<NetworkArgs> = OnceLock::new();
+
+pub fn set_network_args(args: NetworkArgs) {
+    let _ = NETWORK_ARGS.set(args);
+}
+
+// Where radyo.toml lives, e.g. ~/.config/radyo/radyo.toml on Linux
+pub fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("radyo.toml"))
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let mut config = Self::load_from(config_path()?)?;
+        if let Some(args) = NETWORK_ARGS.get() {
+            config.apply_network_args(args);
+        }
+        Ok(config)
+    }
+
+    // A missing file means every setting has its default
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Flags win over the file; clap keeps --local, --relay and --relay-mode apart
+    fn apply_network_args(&mut self, args: &NetworkArgs) {
+        self.local |= args.local;
+        if !args.relays.is_empty() {
+            self.relay = RelaySettings { mode: RelayChoice::Custom, urls: args.relays.iter().map(ToString::to_string).collect() };
+        }
+        if let Some(mode) = args.relay_mode {
+            self.relay.mode = mode;
+        }
+        if !args.discovery.is_empty() {
+            self.discovery = args.discovery.clone();
+        }
+    }
+
+    /// Sets one setting in `radyo.toml`, e.g. `timeouts.dial` to `45`.
+    ///
+    /// Values are read as TOML, falling back to a plain string, so
+    /// `ringtone nana_ringtone` needs no quotes. The rest of the file is
+    /// left as it was.
+    pub fn set(key: &str, value: &str) -> Result<Self> {
+        Self::set_in(config_path()?, key, value)
+    }
+
+    pub fn set_in(path: impl AsRef<Path>, key: &str, value: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let mut table: toml::Table = match std::fs::read_to_string(path) {
+            Ok(text) => text.parse().with_context(|| format!("Invalid config file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let (sections, name) = match key.rsplit_once('.') {
+            Some((sections, name)) => (sections.split('.').collect(), name),
+            None => (Vec::new(), key),
+        };
+        let mut section = &mut table;
+        for part in sections {
+            section = section
+                .entry(part)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| anyhow::anyhow!("{} is a setting, not a section", part))?;
+        }
+        section.insert(name.to_string(), parse_value(value));
+
+        let config: Self = toml::Value::Table(table.clone())
+            .try_into()
+            .with_context(|| format!("Can't set {} to {}", key, value))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(&table)?)?;
+        Ok(config)
+    }
+
+    /// An endpoint builder using these relay and discovery settings, or
+    /// mDNS alone when `local` is set.
+    pub fn endpoint_builder(&self) -> Result<Builder> {
+        if self.local {
+            info!("Local network only - no relays, finding nodes with mDNS");
+            return Ok(endpoint_builder(true, RelayMode::Disabled, &[]));
+        }
+        Ok(endpoint_builder(false, self.relay.relay_mode()?, &self.discovery))
+    }
+
+    /// Whether the endpoint has relay servers to reach it through.
+    pub fn uses_relays(&self) -> bool {
+        !self.local && self.relay.mode != RelayChoice::Disabled
+    }
+
+    /// The phone's secret key from `identity`, created on first use.
+    pub fn secret_key(&self) -> Result<Option<SecretKey>> {
+        self.identity
+            .as_ref()
+            .map(|path| load_identity(&config_dir()?.join(path)))
+            .transpose()
+    }
+}
+
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {}", value)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+// Read the node's secret key, creating it on first use
+fn load_identity(path: &Path) -> Result<SecretKey> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let bytes: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Invalid identity file {} - expected a 32-byte key", path.display()))?;
+            Ok(SecretKey::from_bytes(&bytes))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = SecretKey::generate(rand::rngs::OsRng);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            write_private(path, &key.to_bytes())?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(bytes)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A radyo.toml path in a fresh directory of its own
+    fn config_file(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("radyo-config-{}-{}", std::process::id(), test));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("radyo.toml")
+    }
+
+    #[test]
+    fn sets_a_top_level_setting_without_quotes() {
+        let path = config_file("top_level");
+        let config = Config::set_in(&path, "ringtone", "nana_ringtone").unwrap();
+        assert_eq!(config.ringtone, "nana_ringtone");
+        assert_eq!(Config::load_from(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn sets_nested_keys_and_keeps_the_rest() {
+        let path = config_file("nested");
+        Config::set_in(&path, "timeouts.dial", "45").unwrap();
+        Config::set_in(&path, "audio.input_device", "USB Headset").unwrap();
+        let config = Config::set_in(&path, "dnd.schedule", r#"[{ from = "22:00", to = "07:00" }]"#).unwrap();
+
+        assert_eq!(config.timeouts.dial, 45);
+        assert_eq!(config.timeouts.connect, CONNECT_TIMEOUT.as_secs());
+        assert_eq!(config.audio.input_device.as_deref(), Some("USB Headset"));
+        assert_eq!(config.dnd.schedule.len(), 1);
+        assert_eq!(Config::load_from(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn reads_values_as_toml() {
+        let path = config_file("toml_values");
+        let config = Config::set_in(&path, "discovery", r#"["n0", "dht"]"#).unwrap();
+        assert_eq!(config.discovery, vec![DiscoveryService::N0, DiscoveryService::Dht]);
+        let config = Config::set_in(&path, "local", "true").unwrap();
+        assert!(config.local);
+    }
+
+    #[test]
+    fn type_errors_leave_the_file_alone() {
+        let path = config_file("type_errors");
+        Config::set_in(&path, "timeouts.dial", "45").unwrap();
+        let before = std::fs::read_to_string(&path).unwrap();
+
+        assert!(Config::set_in(&path, "timeouts.dial", "soon").is_err());
+        assert!(Config::set_in(&path, "audio.volume", "-5").is_err());
+        assert!(Config::set_in(&path, "relay.mode", "sometimes").is_err());
+        assert!(Config::set_in(&path, "timeouts.never", "5").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    }
+
+    fn from_file() -> Config {
+        Config {
+            local: false,
+            discovery: vec![DiscoveryService::Dht],
+            relay: RelaySettings { mode: RelayChoice::Custom, urls: vec!["https://relay.example.com".to_string()] },
+            ..Config::default()
+        }
+    }
+
+    fn relay(url: &str) -> RelayUrl {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn no_network_flags_keep_the_file() {
+        let mut config = from_file();
+        config.apply_network_args(&NetworkArgs::default());
+        assert_eq!(config, from_file());
+    }
+
+    #[test]
+    fn relay_flags_replace_the_relays_in_the_file() {
+        let mut config = from_file();
+        config.apply_network_args(&NetworkArgs { relays: vec![relay("https://other.example.com")], ..Default::default() });
+        assert_eq!(config.relay.mode, RelayChoice::Custom);
+        assert_eq!(config.relay.urls.len(), 1);
+        assert!(config.relay.urls[0].starts_with("https://other.example.com"), "{:?}", config.relay.urls);
+        assert!(config.relay.relay_mode().is_ok());
+    }
+
+    #[test]
+    fn relay_mode_flag_keeps_the_relays_in_the_file() {
+        let mut config = from_file();
+        config.apply_network_args(&NetworkArgs { relay_mode: Some(RelayChoice::Disabled), ..Default::default() });
+        assert_eq!(config.relay.mode, RelayChoice::Disabled);
+        assert_eq!(config.relay.urls, from_file().relay.urls);
+        assert!(!config.uses_relays());
+
+        // Switching back to custom picks the file's relays up again
+        config.apply_network_args(&NetworkArgs { relay_mode: Some(RelayChoice::Custom), ..Default::default() });
+        assert!(config.relay.relay_mode().is_ok());
+    }
+
+    #[test]
+    fn custom_relay_mode_without_relays_is_an_error() {
+        let mut config = Config::default();
+        config.apply_network_args(&NetworkArgs { relay_mode: Some(RelayChoice::Custom), ..Default::default() });
+        assert!(config.endpoint_builder().is_err());
+    }
+
+    #[test]
+    fn discovery_flag_replaces_the_list_and_local_only_adds() {
+        let mut config = from_file();
+        config.apply_network_args(&NetworkArgs { discovery: vec![DiscoveryService::N0, DiscoveryService::Local], ..Default::default() });
+        assert_eq!(config.discovery, vec![DiscoveryService::N0, DiscoveryService::Local]);
+
+        config.apply_network_args(&NetworkArgs { local: true, ..Default::default() });
+        assert!(config.local);
+        assert!(!config.uses_relays());
+        // --local can't be turned off from the command line once radyo.toml sets it
+        config.apply_network_args(&NetworkArgs::default());
+        assert!(config.local);
+    }
+
+    #[test]
+    fn settings_are_not_sections() {
+        let path = config_file("not_sections");
+        Config::set_in(&path, "ringtone", "nana_ringtone").unwrap();
+        let err = Config::set_in(&path, "ringtone.name", "nana_ringtone").unwrap_err();
+        assert!(err.to_string().contains("ringtone is a setting"), "{}", err);
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::Result;
+use iroh::endpoint::{ConnectError, ConnectWithOptsError, Connection, ConnectionError, TransportErrorCode};
+use iroh::{Endpoint, NodeAddr};
+use std::fmt;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::call::CallManager;
+use crate::contacts::ContactBook;
+use crate::protocol::ALPN;
+
+// The longest we wait between two connection attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// TLS alert the other side sends when it doesn't speak any ALPN we offered
+const NO_APPLICATION_PROTOCOL: u8 = 120;
+
+/// Why a node couldn't be reached.
+///
+/// Dial errors carry one of these, so callers can tell an offline node from
+/// one that isn't a phone: `error.downcast_ref::<DialError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialError {
+    /// No address or path to the node - it is offline or unreachable
+    NoRoute,
+    /// The node is up, but doesn't run radyo
+    AlpnNotSupported,
+    /// Nothing came back within the connect timeout
+    Timeout(Duration),
+    /// The node turned the connection away
+    Rejected(String),
+    Other(String),
+}
+
+impl DialError {
+    // Only a node we couldn't find might answer next time
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DialError::NoRoute | DialError::Timeout(_))
+    }
+}
+
+impl fmt::Display for DialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialError::NoRoute => f.write_str("no route to the node - it may be offline"),
+            DialError::AlpnNotSupported => f.write_str("the node is online but isn't running radyo"),
+            DialError::Timeout(timeout) => write!(f, "no answer within {:?}", timeout),
+            DialError::Rejected(reason) => write!(f, "the node refused the connection ({})", reason),
+            DialError::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for DialError {}
+
+impl From<ConnectError> for DialError {
+    fn from(e: ConnectError) -> Self {
+        match &e {
+            ConnectError::Connect { source, .. } => match source.as_ref() {
+                ConnectWithOptsError::NoAddress { .. } | ConnectWithOptsError::AddNodeAddr { .. } => DialError::NoRoute,
+                _ => DialError::Other(e.to_string()),
+            },
+            ConnectError::Connection { source, .. } => match source.as_ref() {
+                ConnectionError::ConnectionClosed(close)
+                    if close.error_code == TransportErrorCode::crypto(NO_APPLICATION_PROTOCOL) =>
+                {
+                    DialError::AlpnNotSupported
+                }
+                ConnectionError::TimedOut => DialError::NoRoute,
+                ConnectionError::ConnectionClosed(_) | ConnectionError::ApplicationClosed(_) | ConnectionError::Reset => {
+                    DialError::Rejected(source.to_string())
+                }
+                _ => DialError::Other(e.to_string()),
+            },
+            _ => DialError::Other(e.to_string()),
+        }
+    }
+}
+
+/// Connects to a node on the radyo ALPN.
+///
+/// Each attempt gives up after the connect timeout. Nodes that can't be
+/// found are retried with exponential backoff; a node that refuses us or
+/// doesn't run radyo is not.
+pub async fn connect(endpoint: &Endpoint, node_addr: NodeAddr) -> Result<Connection> {
+    let retry = CallManager::retry();
+    let timeout = CallManager::timeouts().connect();
+    let mut backoff = retry.backoff();
+    let mut attempt = 0;
+    // Only needed once something goes wrong
+    let mut name = None;
+    loop {
+        attempt += 1;
+        let error = match tokio::time::timeout(timeout, endpoint.connect(node_addr.clone(), ALPN)).await {
+            Ok(Ok(conn)) => return Ok(conn),
+            Ok(Err(e)) => DialError::from(e),
+            Err(_) => DialError::Timeout(timeout),
+        };
+        let name = name.get_or_insert_with(|| ContactBook::load_or_default().display_name(node_addr.node_id));
+        if !error.is_retryable() || attempt > retry.retries {
+            return Err(anyhow::Error::new(error).context(format!("Couldn't reach {}", name)));
+        }
+        warn!("Couldn't reach {}: {} - retry {} of {} in {:?}", name, error, attempt, retry.retries, backoff);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iroh::endpoint::RelayMode;
+    use iroh::{SecretKey, Watcher};
+
+    async fn endpoint(alpn: &[u8]) -> Result<Endpoint> {
+        Ok(Endpoint::builder().relay_mode(RelayMode::Disabled).alpns(vec![alpn.to_vec()]).bind().await?)
+    }
+
+    // Dial `server` on the radyo ALPN while it handles the connection with `answer`
+    async fn dial_error(server: &Endpoint, answer: impl AsyncFnOnce(iroh::endpoint::Incoming)) -> Result<DialError> {
+        let client = endpoint(b"unused").await?;
+        let server_addr = server.node_addr().initialized().await;
+        let accept = async {
+            let incoming = server.accept().await.expect("server endpoint closed");
+            answer(incoming).await;
+        };
+        let (result, ()) = tokio::join!(client.connect(server_addr, ALPN), accept);
+        match result {
+            Ok(_) => anyhow::bail!("the connection went through"),
+            Err(e) => Ok(DialError::from(e)),
+        }
+    }
+
+    #[tokio::test]
+    async fn node_without_addresses_has_no_route() -> Result<()> {
+        let client = endpoint(ALPN).await?;
+        let nowhere = NodeAddr::new(SecretKey::generate(rand::rngs::OsRng).public());
+        let error = DialError::from(client.connect(nowhere, ALPN).await.unwrap_err());
+        assert_eq!(error, DialError::NoRoute);
+        assert!(error.is_retryable());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn node_without_radyo_is_told_apart() -> Result<()> {
+        let server = endpoint(b"not-radyo").await?;
+        let error = dial_error(&server, async |incoming| {
+            let _ = incoming.await;
+        })
+        .await?;
+        assert_eq!(error, DialError::AlpnNotSupported);
+        assert!(!error.is_retryable());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refused_connection_is_rejected() -> Result<()> {
+        let server = endpoint(ALPN).await?;
+        let error = dial_error(&server, async |incoming| incoming.refuse()).await?;
+        assert!(matches!(error, DialError::Rejected(_)), "{:?}", error);
+        assert!(!error.is_retryable());
+        Ok(())
+    }
+
+    #[test]
+    fn only_timeouts_and_missing_routes_are_retried() {
+        assert!(DialError::Timeout(Duration::from_secs(15)).is_retryable());
+        assert!(!DialError::Other("boom".to_string()).is_retryable());
+        assert_eq!(DialError::Timeout(Duration::from_secs(15)).to_string(), "no answer within 15s");
+    }
+}
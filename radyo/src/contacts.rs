@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use iroh::NodeId;
+use iroh_base::ticket::NodeTicket;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A named node the user can call instead of pasting its ticket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    pub name: String,
+    pub ticket: NodeTicket,
+    /// A group such as "family", which can have a ringtone of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Rings for this contact instead of the group's or the default ringtone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ringtone: Option<String>,
+}
+
+impl Contact {
+    pub fn node_id(&self) -> NodeId {
+        self.ticket.node_addr().node_id
+    }
+}
+
+/// Settings shared by every contact in a group.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Group {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ringtone: Option<String>,
+}
+
+/// The user's address book, stored as `contacts.toml` in the radyo config dir.
+#[derive(Debug, Default)]
+pub struct ContactBook {
+    path: PathBuf,
+    contacts: Vec<Contact>,
+    groups: BTreeMap<String, Group>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ContactsFile {
+    #[serde(default, rename = "contact")]
+    contacts: Vec<Contact>,
+    #[serde(default, rename = "group", skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<String, Group>,
+}
+
+// Where radyo keeps its settings, e.g. ~/.config/radyo on Linux
+pub fn config_dir() -> Result<PathBuf> {
+    let dir = dirs_next::config_dir().ok_or_else(|| anyhow::anyhow!("no config directory"))?;
+    Ok(dir.join("radyo"))
+}
+
+impl ContactBook {
+    pub fn load() -> Result<Self> {
+        Self::load_from(config_dir()?.join("contacts.toml"))
+    }
+
+    // For naming nodes in the log - a broken contacts.toml just means short node IDs
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_default()
+    }
+
+    // A missing file is just an empty address book
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file: ContactsFile = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("Invalid contacts file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ContactsFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, contacts: file.contacts, groups: file.groups })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = ContactsFile { contacts: self.contacts.clone(), groups: self.groups.clone() };
+        std::fs::write(&self.path, toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    // Adding an existing name replaces its ticket
+    pub fn add(&mut self, name: String, ticket: NodeTicket) {
+        match self.contacts.iter_mut().find(|c| c.name == name) {
+            Some(contact) => contact.ticket = ticket,
+            None => self.contacts.push(Contact { name, ticket, group: None, ringtone: None }),
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Contact> {
+        self.contacts.iter_mut().find(|c| c.name == name)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.get(name)
+    }
+
+    // Setting a group's ringtone creates the group; clearing it leaves no trace
+    pub fn set_group_ringtone(&mut self, group: &str, ringtone: Option<String>) {
+        match ringtone {
+            Some(ringtone) => self.groups.entry(group.to_string()).or_default().ringtone = Some(ringtone),
+            None => {
+                self.groups.remove(group);
+            }
+        }
+    }
+
+    /// The ringtone for a caller: their own, else their group's, if they have one.
+    pub fn ringtone_for(&self, node_id: NodeId) -> Option<String> {
+        let contact = self.find_by_node(node_id)?;
+        contact.ringtone.clone().or_else(|| {
+            let group = contact.group.as_deref()?;
+            self.group(group)?.ringtone.clone()
+        })
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.contacts.len();
+        self.contacts.retain(|c| c.name != name);
+        self.contacts.len() != before
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Contact> {
+        self.contacts.iter().find(|c| c.name == name)
+    }
+
+    pub fn find_by_node(&self, node_id: NodeId) -> Option<&Contact> {
+        self.contacts.iter().find(|c| c.node_id() == node_id)
+    }
+
+    /// Turns a contact name or a raw node ticket into a ticket to dial.
+    pub fn resolve(&self, target: &str) -> Result<NodeTicket> {
+        if let Some(contact) = self.get(target) {
+            return Ok(contact.ticket.clone());
+        }
+        target
+            .parse()
+            .map_err(|_| anyhow::anyhow!("'{}' is neither a contact nor a valid node ticket", target))
+    }
+
+    // Contact name for a node if we know it, otherwise its short node ID
+    pub fn display_name(&self, node_id: NodeId) -> String {
+        self.find_by_node(node_id)
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| node_id.fmt_short())
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::Result;
+use iroh::Endpoint;
+use iroh_base::ticket::NodeTicket;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+
+use crate::call::{answer_call, dial, send_command_to, subscribe_events, CallCommand, CallDirection, CallEvent, CallManager, CallPhase};
+use crate::contacts::ContactBook;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// The request was fine, but the phone couldn't do it
+const CALL_FAILED: i64 = -32000;
+
+/// Where the daemon listens by default, e.g. /run/user/1000/radyo.sock
+pub fn default_socket_path() -> Result<PathBuf> {
+    let dir = dirs_next::runtime_dir()
+        .or_else(|| dirs_next::data_dir().map(|dir| dir.join("radyo")))
+        .ok_or_else(|| anyhow::anyhow!("no runtime or data directory for the control socket"))?;
+    Ok(dir.join("radyo.sock"))
+}
+
+/// Serves the phone's control socket.
+///
+/// Each line a client writes is one JSON-RPC 2.0 request, answered with one
+/// line unless it is a notification without an `id`. Replies to `dial` come
+/// once the call is placed, possibly after replies to later requests.
+/// Methods: `dial {target}`, `answer`, `decline` and `hangup` (each with an
+/// optional `call_id`), `status`, `dnd {enabled}` (flipped if left out), and
+/// `subscribe`, after which call events arrive as `event` notifications.
+pub struct ControlSocket {
+    path: PathBuf,
+    listener: UnixListener,
+}
+
+impl ControlSocket {
+    /// Listens on `path`, replacing a socket a crashed daemon left behind.
+    pub async fn bind(path: &Path) -> Result<Self> {
+        if UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("a radyo daemon is already listening on {}", path.display());
+        }
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(Self { path: path.to_owned(), listener })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Answers clients until the task is dropped.
+    pub async fn serve(&self, endpoint: Endpoint, ticket: NodeTicket) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_client(stream, endpoint.clone(), ticket.clone()));
+                }
+                Err(e) => warn!("Control socket error: {}", e),
+            }
+        }
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    // None for a notification, which gets no reply; an explicit null is still a request
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(CALL_FAILED, e.to_string())
+    }
+}
+
+async fn handle_client(stream: UnixStream, endpoint: Endpoint, ticket: NodeTicket) {
+    let (read, mut write) = stream.into_split();
+    // Replies and event notifications share the socket, one line each
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = out_rx.recv().await {
+            let line = format!("{}\n", message);
+            if write.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(read).lines();
+    let mut subscription = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Request { id, method, params } = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                if !reply(&out_tx, Some(Value::Null), Err(RpcError::new(PARSE_ERROR, e.to_string()))) {
+                    break;
+                }
+                continue;
+            }
+        };
+        let result = match method.as_str() {
+            "subscribe" => {
+                subscription.get_or_insert_with(|| tokio::spawn(forward_events(subscribe_events(), out_tx.clone())));
+                Ok(json!(true))
+            }
+            // Dialing waits for the other side, so it mustn't hold up the next request
+            "dial" => {
+                let (endpoint, out_tx) = (endpoint.clone(), out_tx.clone());
+                tokio::spawn(async move {
+                    let result = dial_target(&params, &endpoint).await;
+                    reply(&out_tx, id, result);
+                });
+                continue;
+            }
+            _ => handle_request(&method, &params, &ticket),
+        };
+        if !reply(&out_tx, id, result) {
+            break;
+        }
+    }
+
+    if let Some(subscription) = subscription {
+        subscription.abort();
+    }
+    drop(out_tx);
+    let _ = writer.await;
+}
+
+// Queue the response for the writer, returning false once the client is gone
+fn reply(out: &mpsc::UnboundedSender<Value>, id: Option<Value>, result: Result<Value, RpcError>) -> bool {
+    match id {
+        Some(id) => out.send(response(id, result)).is_ok(),
+        None => true,
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    }
+}
+
+async fn dial_target(params: &Value, endpoint: &Endpoint) -> Result<Value, RpcError> {
+    let target = params
+        .get("target")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "dial needs a target contact or ticket"))?;
+    let target = ContactBook::load()?.resolve(target)?;
+    let call = dial(endpoint, target.node_addr().clone()).await?;
+    let call_id = call.call_id();
+    call.spawn();
+    Ok(json!({ "call_id": call_id }))
+}
+
+fn handle_request(method: &str, params: &Value, ticket: &NodeTicket) -> Result<Value, RpcError> {
+    match method {
+        "answer" => {
+            let call_id = ringing_call(params)?;
+            delivered_or(answer_call(call_id), call_id)
+        }
+        "decline" => {
+            let call_id = ringing_call(params)?;
+            delivered_or(send_command_to(call_id, CallCommand::Decline), call_id)
+        }
+        "hangup" => {
+            let call_id = call_param(params)?
+                .or_else(CallManager::focused_call)
+                .ok_or_else(|| RpcError::new(CALL_FAILED, "no call to hang up"))?;
+            delivered_or(send_command_to(call_id, CallCommand::Hangup), call_id)
+        }
+        "status" => {
+            let focused = CallManager::focused_call();
+            let calls: Vec<Value> = CallManager::active_calls()
+                .into_iter()
+                .map(|(call_id, phase)| json!({ "call_id": call_id, "phase": phase_name(phase), "focused": focused == Some(call_id) }))
+                .collect();
+            Ok(json!({ "ticket": ticket.to_string(), "calls": calls, "dnd": CallManager::dnd_active() }))
+        }
+        "dnd" => {
+            let enabled = match params.get("enabled") {
+                None | Some(Value::Null) => CallManager::toggle_do_not_disturb(),
+                Some(enabled) => {
+                    let enabled = enabled
+                        .as_bool()
+                        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "enabled must be true or false"))?;
+                    CallManager::set_do_not_disturb(enabled);
+                    enabled
+                }
+            };
+            Ok(json!({ "enabled": enabled, "active": CallManager::dnd_active() }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method {}", method))),
+    }
+}
+
+fn call_param(params: &Value) -> Result<Option<u128>, RpcError> {
+    match params.get("call_id") {
+        None | Some(Value::Null) => Ok(None),
+        Some(call_id) => call_id
+            .as_u64()
+            .map(|call_id| Some(call_id as u128))
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "call_id must be a number")),
+    }
+}
+
+// The call named in `params`, or the oldest one that is ringing
+fn ringing_call(params: &Value) -> Result<u128, RpcError> {
+    if let Some(call_id) = call_param(params)? {
+        return Ok(call_id);
+    }
+    CallManager::ringing_call().ok_or_else(|| RpcError::new(CALL_FAILED, "no call is ringing"))
+}
+
+fn delivered_or(delivered: bool, call_id: u128) -> Result<Value, RpcError> {
+    if delivered && CallManager::call_phase(call_id).is_some() {
+        Ok(json!({ "call_id": call_id }))
+    } else {
+        Err(RpcError::new(CALL_FAILED, format!("no call CALL-{}", call_id)))
+    }
+}
+
+async fn forward_events(mut events: broadcast::Receiver<(u128, CallEvent)>, out: mpsc::UnboundedSender<Value>) {
+    loop {
+        let (call_id, event) = match events.recv().await {
+            // Level meters update too often to be worth sending over the socket
+            Ok((_, CallEvent::Levels { .. })) => continue,
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let notification = json!({ "jsonrpc": "2.0", "method": "event", "params": event_json(call_id, &event) });
+        if out.send(notification).is_err() {
+            break;
+        }
+    }
+}
+
+fn event_json(call_id: u128, event: &CallEvent) -> Value {
+    match event {
+        CallEvent::Started { remote, direction } => json!({
+            "call_id": call_id,
+            "event": "started",
+            "remote": remote.to_string(),
+            "name": ContactBook::load_or_default().display_name(*remote),
+            "direction": match direction {
+                CallDirection::Incoming => "incoming",
+                CallDirection::Outgoing => "outgoing",
+            },
+        }),
+        CallEvent::PhaseChanged(phase) => json!({ "call_id": call_id, "event": "phase", "phase": phase_name(*phase) }),
+        CallEvent::Dtmf(digit) => json!({ "call_id": call_id, "event": "dtmf", "digit": digit.to_string() }),
+        CallEvent::Muted(muted) => json!({ "call_id": call_id, "event": "muted", "muted": muted }),
+        CallEvent::Stats(stats) => json!({
+            "call_id": call_id,
+            "event": "stats",
+            "rtt_ms": stats.rtt.as_millis() as u64,
+            "path": stats.path.to_string(),
+            "loss_percent": stats.loss_percent(),
+        }),
+        CallEvent::Levels { .. } => Value::Null,
+    }
+}
+
+fn phase_name(phase: CallPhase) -> &'static str {
+    match phase {
+        CallPhase::Ringing => "ringing",
+        CallPhase::Waiting => "waiting",
+        CallPhase::Active => "active",
+        CallPhase::OnHold { by_us: true } => "holding",
+        CallPhase::OnHold { by_us: false } => "held",
+        CallPhase::Ended => "ended",
+    }
+}
+
+/// A connection to a running daemon's control socket.
+pub struct DaemonClient {
+    lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    write: tokio::net::unix::OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl DaemonClient {
+    pub async fn connect(path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("no radyo daemon on {} ({}) - start one with `radyo daemon`", path.display(), e))?;
+        let (read, write) = stream.into_split();
+        Ok(Self { lines: BufReader::new(read).lines(), write, next_id: 0 })
+    }
+
+    /// Calls `method` and returns its result, or the daemon's error.
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params });
+        self.write.write_all(format!("{}\n", request).as_bytes()).await?;
+        loop {
+            let message = self.next_message().await?;
+            // Skip event notifications that arrive before the reply
+            if message.get("id") != Some(&json!(self.next_id)) {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+                anyhow::bail!("{}", text);
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// The next call event, once subscribed.
+    pub async fn next_event(&mut self) -> Result<Value> {
+        loop {
+            let message = self.next_message().await?;
+            if message.get("method").and_then(Value::as_str) == Some("event") {
+                return Ok(message.get("params").cloned().unwrap_or(Value::Null));
+            }
+        }
+    }
+
+    async fn next_message(&mut self) -> Result<Value> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("the daemon closed the connection"))?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::NaiveTime;
+use iroh::NodeId;
+use iroh_base::ticket::NodeTicket;
+use serde::{Deserialize, Serialize};
+
+/// A daily stretch of time when calls don't ring, e.g. 22:00 to 07:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl QuietHours {
+    // Hours that end before they start run past midnight
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            time >= self.from || time < self.to
+        }
+    }
+}
+
+/// When do-not-disturb turns calls away by itself, and who rings anyway.
+#[derive(Debug, Clone, Default)]
+pub struct DndRules {
+    pub schedule: Vec<QuietHours>,
+    /// Send calls here without ringing instead of declining them
+    pub voicemail: Option<NodeTicket>,
+    /// Callers who ring through even while do-not-disturb is on
+    pub favourites: Vec<NodeId>,
+}
+
+impl DndRules {
+    pub fn in_quiet_hours(&self, time: NaiveTime) -> bool {
+        self.schedule.iter().any(|hours| hours.contains(time))
+    }
+
+    pub fn lets_through(&self, caller: NodeId) -> bool {
+        self.favourites.contains(&caller)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    fn hours(from: &str, to: &str) -> QuietHours {
+        QuietHours { from: at(from), to: at(to) }
+    }
+
+    #[test]
+    fn daytime_hours_include_the_start_but_not_the_end() {
+        let lunch = hours("12:00", "13:30");
+        assert!(!lunch.contains(at("11:59")));
+        assert!(lunch.contains(at("12:00")));
+        assert!(lunch.contains(at("13:29")));
+        assert!(!lunch.contains(at("13:30")));
+        assert!(!lunch.contains(at("00:00")));
+    }
+
+    #[test]
+    fn hours_past_midnight_wrap_around() {
+        let night = hours("22:00", "07:00");
+        assert!(!night.contains(at("21:59")));
+        assert!(night.contains(at("22:00")));
+        assert!(night.contains(at("23:59")));
+        assert!(night.contains(at("00:00")));
+        assert!(night.contains(at("06:59")));
+        assert!(!night.contains(at("07:00")));
+        assert!(!night.contains(at("12:00")));
+    }
+
+    #[test]
+    fn hours_that_start_where_they_end_are_empty() {
+        let none = hours("09:00", "09:00");
+        assert!(!none.contains(at("09:00")));
+        assert!(!none.contains(at("21:00")));
+    }
+
+    #[test]
+    fn any_matching_span_is_quiet() {
+        let rules = DndRules { schedule: vec![hours("12:00", "13:00"), hours("23:00", "06:00")], ..Default::default() };
+        assert!(rules.in_quiet_hours(at("12:30")));
+        assert!(rules.in_quiet_hours(at("02:00")));
+        assert!(!rules.in_quiet_hours(at("18:00")));
+        assert!(!DndRules::default().in_quiet_hours(at("02:00")));
+    }
+}
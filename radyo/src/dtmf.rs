@@ -0,0 +1,66 @@
+use std::f32::consts::TAU;
+
+use crate::voice::{FRAME_SAMPLES, VOICE_SAMPLE_RATE};
+
+// Each in-band digit is 100 ms of tone followed by 60 ms of silence
+const TONE_FRAMES: usize = 5;
+const GAP_FRAMES: usize = 3;
+
+// Loud enough to detect, quiet enough not to hurt
+const TONE_AMPLITUDE: f32 = 0.25;
+
+/// The keypad digits DTMF can carry: 0-9, *, # and A-D.
+pub fn is_dtmf_digit(digit: char) -> bool {
+    frequencies(digit).is_some()
+}
+
+// The row and column frequencies of a keypad digit
+fn frequencies(digit: char) -> Option<(f32, f32)> {
+    let (row, col) = match digit.to_ascii_uppercase() {
+        '1' => (697.0, 1209.0),
+        '2' => (697.0, 1336.0),
+        '3' => (697.0, 1477.0),
+        'A' => (697.0, 1633.0),
+        '4' => (770.0, 1209.0),
+        '5' => (770.0, 1336.0),
+        '6' => (770.0, 1477.0),
+        'B' => (770.0, 1633.0),
+        '7' => (852.0, 1209.0),
+        '8' => (852.0, 1336.0),
+        '9' => (852.0, 1477.0),
+        'C' => (852.0, 1633.0),
+        '*' => (941.0, 1209.0),
+        '0' => (941.0, 1336.0),
+        '#' => (941.0, 1477.0),
+        'D' => (941.0, 1633.0),
+        _ => return None,
+    };
+    Some((row, col))
+}
+
+/// The tone for one digit, as 16 kHz voice samples.
+pub fn tone(digit: char) -> Vec<i16> {
+    let Some((row, col)) = frequencies(digit) else {
+        return Vec::new();
+    };
+    (0..TONE_FRAMES * FRAME_SAMPLES)
+        .map(|n| {
+            let t = n as f32 / VOICE_SAMPLE_RATE as f32;
+            let sample = ((TAU * row * t).sin() + (TAU * col * t).sin()) / 2.0 * TONE_AMPLITUDE;
+            (sample * i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+/// The voice frames that play digits in-band, each tone followed by a gap.
+pub fn tone_frames(digits: &str) -> Vec<Vec<i16>> {
+    let silence = vec![0i16; FRAME_SAMPLES];
+    digits
+        .chars()
+        .flat_map(|digit| {
+            let tone = tone(digit);
+            let frames: Vec<Vec<i16>> = tone.chunks(FRAME_SAMPLES).map(<[i16]>::to_vec).collect();
+            frames.into_iter().chain(std::iter::repeat_n(silence.clone(), GAP_FRAMES))
+        })
+        .collect()
+}
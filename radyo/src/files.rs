@@ -0,0 +1,116 @@
+use anyhow::Result;
+use iroh::{Endpoint, NodeId};
+use iroh_blobs::api::downloader::DownloadProgressItem;
+use iroh_blobs::store::mem::MemStore;
+use iroh_blobs::{BlobsProtocol, Hash};
+use n0_future::StreamExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::info;
+
+// Files offered on calls, served to the other side over iroh-blobs.
+// Kept in memory so several radyo processes can run side by side.
+static FILE_STORE: OnceLock<MemStore> = OnceLock::new();
+
+// Print download progress every this many percent
+const PROGRESS_STEP: u64 = 10;
+
+/// A file one side of a call offered to the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOffer {
+    pub hash: Hash,
+    pub size: u64,
+    pub name: String,
+}
+
+impl FileOffer {
+    /// The offered name without any directories, so a download can't land outside the target folder.
+    pub fn file_name(&self) -> String {
+        Path::new(&self.name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| self.hash.fmt_short().to_string())
+    }
+}
+
+fn store() -> &'static MemStore {
+    FILE_STORE.get_or_init(MemStore::new)
+}
+
+/// The iroh-blobs handler that lets call peers fetch the files we offer.
+pub fn blobs_protocol(endpoint: &Endpoint) -> BlobsProtocol {
+    BlobsProtocol::new(store(), endpoint.clone(), None)
+}
+
+/// Adds a file to the store so the other side of a call can download it.
+pub async fn share_file(path: &Path) -> Result<FileOffer> {
+    let path = std::path::absolute(path)?;
+    let size = std::fs::metadata(&path)?.len();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+    let tag = store().add_path(&path).await?;
+    Ok(FileOffer { hash: tag.hash, size, name })
+}
+
+/// Downloads an offered file from `provider` and saves it to the downloads folder.
+pub async fn download_file(endpoint: &Endpoint, provider: NodeId, offer: &FileOffer) -> Result<PathBuf> {
+    let name = offer.file_name();
+    info!("Downloading {} ({})...", name, format_size(offer.size));
+    let downloader = store().downloader(endpoint);
+    let mut progress = downloader.download(offer.hash, Some(provider)).stream().await?;
+    let mut reported = 0;
+    while let Some(item) = progress.next().await {
+        match item {
+            DownloadProgressItem::Progress(bytes) => {
+                let percent = (bytes * 100).checked_div(offer.size).unwrap_or(100).min(100);
+                if percent >= reported + PROGRESS_STEP {
+                    reported = percent - percent % PROGRESS_STEP;
+                    info!("{} {}% ({} / {})", name, percent, format_size(bytes), format_size(offer.size));
+                }
+            }
+            DownloadProgressItem::Error(e) => return Err(e),
+            DownloadProgressItem::DownloadError => anyhow::bail!("the other side stopped sharing {}", name),
+            _ => {}
+        }
+    }
+
+    let target = std::path::absolute(download_path(&name))?;
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    store().export(offer.hash, &target).await?;
+    Ok(target)
+}
+
+// The downloads folder, or the current one - without overwriting an earlier download of the same name
+fn download_path(name: &str) -> PathBuf {
+    let dir = dirs_next::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("some file name is free")
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let bytes_f = bytes as f64;
+    if bytes_f >= KB * KB * KB {
+        format!("{:.1} GB", bytes_f / (KB * KB * KB))
+    } else if bytes_f >= KB * KB {
+        format!("{:.1} MB", bytes_f / (KB * KB))
+    } else if bytes_f >= KB {
+        format!("{:.1} KB", bytes_f / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
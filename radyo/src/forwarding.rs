@@ -0,0 +1,37 @@
+use iroh_base::ticket::NodeTicket;
+use std::time::Duration;
+
+// How long an incoming call rings before the no-answer rule forwards it
+pub const DEFAULT_NO_ANSWER_TIMEOUT: Duration = Duration::from_secs(20);
+
+// How many times a dialer follows REDIRECT before giving up on a forwarding loop
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Where to send callers instead of ringing here.
+#[derive(Debug, Clone)]
+pub struct ForwardingRules {
+    /// Forward every call without ringing
+    pub always: Option<NodeTicket>,
+    /// Forward callers who would otherwise get BUSY
+    pub on_busy: Option<NodeTicket>,
+    /// Forward calls nobody answers within `no_answer_timeout`
+    pub on_no_answer: Option<NodeTicket>,
+    pub no_answer_timeout: Duration,
+}
+
+impl Default for ForwardingRules {
+    fn default() -> Self {
+        Self {
+            always: None,
+            on_busy: None,
+            on_no_answer: None,
+            no_answer_timeout: DEFAULT_NO_ANSWER_TIMEOUT,
+        }
+    }
+}
+
+impl ForwardingRules {
+    pub fn is_empty(&self) -> bool {
+        self.always.is_none() && self.on_busy.is_none() && self.on_no_answer.is_none()
+    }
+}
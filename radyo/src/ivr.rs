@@ -0,0 +1,330 @@
+use anyhow::Result;
+use iroh::endpoint::{Connection, SendStream};
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use n0_future::boxed::BoxFuture;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{error, info, Instrument};
+
+use crate::call::{call_span, close_and_wait, forward_call, new_call_id, CallManager, CallRecord};
+use crate::chat::ChatLog;
+use crate::contacts::ContactBook;
+use crate::presence::{answer_presence, Presence};
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+use crate::recording::{CallRecorder, RecordingMode};
+use crate::station::decode_track;
+use crate::voice::{decode_frame, encode_frame, FRAME_SAMPLES};
+
+// Prompts go out one 20 ms frame at a time, like live voice
+const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Answers incoming calls in code instead of ringing.
+///
+/// Register one with [`crate::RadyoProtocol::with_handler`]. Each call is
+/// handled on its own task; the call rings until the handler answers,
+/// rejects or forwards it. Any async closure taking an [`IvrCall`] works:
+///
+/// ```text
+/// let bot = |mut call: IvrCall| async move {
+///     call.answer().await?;
+///     call.play("ringtons/lost_woods.mp3").await?;
+///     call.hangup().await
+/// };
+/// RadyoProtocol::with_handler(endpoint, bot);
+/// ```
+pub trait CallHandler: Send + Sync + 'static {
+    fn handle(&self, call: IvrCall) -> BoxFuture<Result<()>>;
+}
+
+impl<F, Fut> CallHandler for F
+where
+    F: Fn(IvrCall) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    fn handle(&self, call: IvrCall) -> BoxFuture<Result<()>> {
+        Box::pin(self(call))
+    }
+}
+
+/// An incoming call driven by a [`CallHandler`].
+///
+/// Keypad digits the caller presses are queued, so none are lost while a
+/// prompt plays. Playing and recording need the call answered first. Once
+/// the caller hangs up every method returns an error.
+/// A call the handler drops without hanging up is hung up for it.
+pub struct IvrCall {
+    call_id: u128,
+    conn: Connection,
+    caller_name: String,
+    send: Option<SendStream>,
+    signals: SignalReader,
+    digits: VecDeque<char>,
+    answered: bool,
+    ended: bool,
+}
+
+impl IvrCall {
+    pub fn call_id(&self) -> u128 {
+        self.call_id
+    }
+
+    pub fn caller(&self) -> Result<NodeId> {
+        Ok(self.conn.remote_node_id()?)
+    }
+
+    /// The caller's contact name, or their short node ID.
+    pub fn caller_name(&self) -> String {
+        self.caller_name.clone()
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    pub async fn answer(&mut self) -> Result<()> {
+        self.ensure_open()?;
+        if !self.answered {
+            self.send(&Signal::Answer).await?;
+            self.answered = true;
+            info!("Answered {}", self.caller_name());
+        }
+        Ok(())
+    }
+
+    /// Turns the caller away with BUSY.
+    pub async fn reject(mut self) -> Result<()> {
+        self.ensure_open()?;
+        info!("Rejecting {}", self.caller_name());
+        self.send(&Signal::Busy).await?;
+        self.ended = true;
+        close_and_wait(&mut self.send.take().expect("open until ended")).await;
+        Ok(())
+    }
+
+    /// Sends an unanswered call on to `target`, like call forwarding.
+    pub async fn forward(mut self, target: &NodeTicket) -> Result<()> {
+        self.ensure_open()?;
+        if self.answered {
+            anyhow::bail!("the call is already answered - use transfer instead");
+        }
+        self.ended = true;
+        forward_call(&mut self.send.take().expect("open until ended"), target).await
+    }
+
+    /// Hands an answered call over to `target`: the caller dials it, then hangs up on us.
+    pub async fn transfer(mut self, target: &NodeTicket) -> Result<()> {
+        self.ensure_open()?;
+        if !self.answered {
+            anyhow::bail!("the call isn't answered yet - use forward instead");
+        }
+        info!("Transferring {} to {}", self.caller_name(), target.node_addr().node_id.fmt_short());
+        self.send(&Signal::Transfer(target.clone())).await?;
+        // The caller hangs up once the target is ringing
+        let _ = tokio::time::timeout(CallManager::timeouts().dial(), async {
+            while !self.ended {
+                self.next_signal().await?;
+            }
+            anyhow::Ok(())
+        })
+        .await;
+        self.hangup().await
+    }
+
+    /// Plays an audio file to the caller.
+    ///
+    /// Stops early when the caller presses a key, which stays queued for
+    /// [`read_digit`](Self::read_digit), or hangs up.
+    pub async fn play(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.ensure_answered()?;
+        let path = path.as_ref().to_owned();
+        let samples = tokio::task::spawn_blocking(move || decode_track(&path)).await??;
+        let mut tick = tokio::time::interval(FRAME_INTERVAL);
+        for (seq, frame) in samples.chunks(FRAME_SAMPLES).enumerate() {
+            // Signals that arrive while we wait don't cost the frame its turn
+            loop {
+                if self.ended || !self.digits.is_empty() {
+                    return Ok(());
+                }
+                tokio::select! {
+                    _ = tick.tick() => break,
+                    signal = self.signals.next() => self.on_signal(signal?).await?,
+                }
+            }
+            self.conn.send_datagram(encode_frame(seq as u32, frame))?;
+        }
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for the caller to press a key.
+    pub async fn read_digit(&mut self, timeout: Duration) -> Result<Option<char>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(digit) = self.digits.pop_front() {
+                return Ok(Some(digit));
+            }
+            self.ensure_open()?;
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => return Ok(None),
+                signal = self.signals.next() => self.on_signal(signal?).await?,
+            }
+        }
+    }
+
+    /// Collects up to `max` digits, stopping early at `#` or when the caller
+    /// pauses for longer than `timeout`. The `#` isn't included.
+    pub async fn read_digits(&mut self, max: usize, timeout: Duration) -> Result<String> {
+        let mut digits = String::new();
+        while digits.len() < max {
+            match self.read_digit(timeout).await? {
+                Some('#') | None => break,
+                Some(digit) => digits.push(digit),
+            }
+        }
+        Ok(digits)
+    }
+
+    /// Records the caller to a WAV file until they press `#`, hang up, or
+    /// `max_length` passes, and returns where it was saved.
+    pub async fn record(&mut self, max_length: Duration) -> Result<PathBuf> {
+        self.ensure_answered()?;
+        let mut recorder = CallRecorder::create(self.call_id, RecordingMode::Mixed)?;
+        info!("Recording {}...", self.caller_name());
+        let deadline = tokio::time::Instant::now() + max_length;
+        self.digits.clear();
+        while !self.ended && !self.digits.contains(&'#') {
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => break,
+                datagram = self.conn.read_datagram() => {
+                    let Ok(datagram) = datagram else { break };
+                    if let Some((_seq, samples)) = decode_frame(&datagram) {
+                        recorder.record_remote(&samples)?;
+                    }
+                }
+                signal = self.signals.next() => self.on_signal(signal?).await?,
+            }
+        }
+        self.digits.retain(|digit| *digit != '#');
+        let path = recorder.finish()?;
+        info!("Saved {}'s message to {}", self.caller_name(), path.display());
+        Ok(path)
+    }
+
+    /// Hangs up and waits briefly for the caller to acknowledge.
+    pub async fn hangup(mut self) -> Result<()> {
+        if self.ended {
+            return Ok(());
+        }
+        self.ended = true;
+        hangup_and_wait(self.send.as_mut().expect("open until ended"), &mut self.signals).await?;
+        info!("Hung up");
+        Ok(())
+    }
+
+    fn ensure_open(&self) -> Result<()> {
+        if self.ended {
+            anyhow::bail!("{} hung up", self.caller_name());
+        }
+        Ok(())
+    }
+
+    // Voice only flows once the caller knows we picked up
+    fn ensure_answered(&self) -> Result<()> {
+        self.ensure_open()?;
+        if !self.answered {
+            anyhow::bail!("answer the call first");
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, signal: &Signal) -> Result<()> {
+        send_signal(self.send.as_mut().expect("open until ended"), signal).await
+    }
+
+    async fn next_signal(&mut self) -> Result<()> {
+        let signal = self.signals.next().await?;
+        self.on_signal(signal).await
+    }
+
+    async fn on_signal(&mut self, signal: Option<Signal>) -> Result<()> {
+        match signal {
+            Some(Signal::Dtmf(digit)) => {
+                info!("{} pressed {}", self.caller_name(), digit);
+                self.digits.push_back(digit);
+            }
+            Some(Signal::Hangup) => {
+                self.ended = true;
+                info!("{} hung up", self.caller_name());
+                self.send(&Signal::HangupAck).await?;
+            }
+            Some(_) => {}
+            None => self.ended = true,
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IvrCall {
+    fn drop(&mut self) {
+        if self.ended {
+            return;
+        }
+        if let Some(mut send) = self.send.take() {
+            let conn = self.conn.clone();
+            tokio::spawn(async move {
+                let _ = send_signal(&mut send, &Signal::Hangup).await;
+                close_and_wait(&mut send).await;
+                drop(conn);
+            });
+        }
+    }
+}
+
+// Accept a call on behalf of `handler`, bypassing the phone's line and ringtone
+pub(crate) async fn handle_bot_call(endpoint: Endpoint, conn: Connection, handler: Arc<dyn CallHandler>) {
+    let call_id = new_call_id();
+    run_bot_call(endpoint, conn, handler, call_id).instrument(call_span(call_id)).await
+}
+
+async fn run_bot_call(endpoint: Endpoint, conn: Connection, handler: Arc<dyn CallHandler>, call_id: u128) {
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    // Presence probes aren't calls, so they stay out of the call history
+    let mut was_call = false;
+    let result = async {
+        let (send, recv) = conn.accept_bi().await?;
+        let mut signals = SignalReader::new(recv);
+        match signals.next().await? {
+            Some(Signal::IncomingCall) => was_call = true,
+            Some(Signal::Presence) => return answer_presence(send, Presence::Available).await,
+            _ => return Ok(()),
+        }
+        let caller_name = conn
+            .remote_node_id()
+            .map(|node_id| ContactBook::load_or_default().display_name(node_id))
+            .unwrap_or_else(|_| "caller".to_string());
+        let call = IvrCall {
+            call_id,
+            conn: conn.clone(),
+            caller_name,
+            send: Some(send),
+            signals,
+            digits: VecDeque::new(),
+            answered: false,
+            ended: false,
+        };
+        info!("Incoming call from {}", call.caller_name());
+        handler.handle(call).await
+    }
+    .await;
+
+    if let Err(e) = result {
+        error!("Call handler error: {}", e);
+    }
+    if was_call {
+        CallManager::record_call(CallRecord::finish(call_id, &endpoint, &conn, started_at, started, &ChatLog::default()));
+    }
+}
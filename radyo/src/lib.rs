@@ -3,11 +3,50 @@ pub mod protocol;
 pub mod call;
 pub mod audio;
 pub mod modes;
+pub mod stats;
+pub mod voice;
+pub mod contacts;
+pub mod forwarding;
+pub mod conference;
+pub mod channel;
+pub mod roster;
+pub mod station;
+pub mod chat;
+pub mod files;
+pub mod recording;
+pub mod dtmf;
+pub mod ivr;
+pub mod client;
+pub mod daemon;
+pub mod tui;
+pub mod logging;
+pub mod config;
+pub mod dnd;
+pub mod presence;
+pub mod connect;
 
-pub use cli::{Cli, Cmd};
-pub use protocol::{RadyoProtocol, ALPN};
-pub use call::{CallManager, CallState};
+pub use cli::{CallArgs, ChannelCmd, Cli, Cmd, ConfigCmd, ContactsCmd, CtlCmd, ForwardArgs, LogArgs};
+pub use network::NetworkArgs;
+pub use protocol::{RadyoProtocol, Signal, ALPN};
+pub use call::{mute, set_volume, subscribe_events, unmute, dial, CallCommand, CallEvent, CallManager, CallOutcome, CallPhase, CallRecord, CallSlot, CallState, OutgoingCall};
+pub use client::{CallEvents, CallHandle, RadyoClient};
+pub use daemon::{ControlSocket, DaemonClient};
 pub use audio::AudioManager;
-pub use modes::{caller_mode, peer_mode};
+pub use modes::{caller_mode, channel_mode, conference_mode, config_mode, contacts_mode, ctl_mode, daemon_mode, peer_mode, tui_mode, station_mode, status_mode, tune_mode, voicemail_mode};
+pub use stats::{CallStats, PathKind};
+pub use voice::{VoiceControls, VoiceLink};
+pub use contacts::{Contact, ContactBook};
+pub use config::Config;
+pub use forwarding::ForwardingRules;
+pub use dnd::{DndRules, QuietHours};
+pub use presence::Presence;
+pub use connect::DialError;
+pub use conference::{Conference, ConferenceCommand};
+pub use channel::{ChannelCommand, ChannelHub, ChannelTicket};
+pub use files::FileOffer;
+pub use ivr::{CallHandler, IvrCall};
+pub use recording::{CallRecorder, RecordingMode};
+pub use chat::{ChatLog, ChatMessage};
+pub use station::{NowPlaying, Station};
 
 pub type Result<T> = anyhow::Result<T>;
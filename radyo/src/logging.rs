@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::cli::LogArgs;
+
+/// Sends radyo's logs to stderr at the level `-v`/`-q` ask for.
+///
+/// Other crates only log errors, or warnings with `-v`, unless `RUST_LOG`
+/// says otherwise, e.g. `RUST_LOG=radyo=debug,iroh=info`. Each call's log
+/// lines carry its ID in a `call` span, so one call can be followed through
+/// a busy log.
+pub fn init(args: LogArgs) -> Result<()> {
+    init_with(args, BoxMakeWriter::new(std::io::stderr), std::io::stderr().is_terminal())
+}
+
+/// Like [`init`], but appends the logs to `path` instead, for the full-screen
+/// UI whose screen they would otherwise scribble over.
+pub fn init_to_file(args: LogArgs, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::options().create(true).append(true).open(path)?;
+    init_with(args, BoxMakeWriter::new(Mutex::new(file)), false)
+}
+
+/// Where the full-screen UI logs to, next to the rest of radyo's data,
+/// e.g. ~/.local/share/radyo/radyo.log
+pub fn log_path() -> Result<PathBuf> {
+    let dir = dirs_next::data_dir().ok_or_else(|| anyhow::anyhow!("no data directory"))?;
+    Ok(dir.join("radyo").join("radyo.log"))
+}
+
+fn init_with(args: LogArgs, writer: BoxMakeWriter, ansi: bool) -> Result<()> {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) if !directives.is_empty() => directives.parse::<Targets>()?,
+        _ => Targets::new()
+            .with_target("radyo", level(args))
+            .with_default(if args.verbose > 0 { LevelFilter::WARN } else { LevelFilter::ERROR }),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_writer(writer)
+        .with_max_level(LevelFilter::TRACE);
+    if args.log_json {
+        builder.json().finish().with(filter).try_init()?;
+    } else {
+        builder
+            .with_ansi(ansi)
+            .with_target(false)
+            .with_timer(ChronoLocal::new("%H:%M:%S%.3f".to_string()))
+            .finish()
+            .with(filter)
+            .try_init()?;
+    }
+    Ok(())
+}
+
+fn level(args: LogArgs) -> LevelFilter {
+    match (args.verbose, args.quiet) {
+        (0, 0) => LevelFilter::INFO,
+        (1, _) => LevelFilter::DEBUG,
+        (_, 0) => LevelFilter::TRACE,
+        (_, 1) => LevelFilter::WARN,
+        _ => LevelFilter::ERROR,
+    }
+}
@@ -1,11 +1,35 @@
 use clap::Parser;
-use radyo::{Cli, Cmd, caller_mode, peer_mode, Result};
+use radyo::{config, logging, Cli, Cmd, caller_mode, channel_mode, conference_mode, config_mode, contacts_mode, ctl_mode, daemon_mode, peer_mode, tui_mode, station_mode, status_mode, tune_mode, voicemail_mode, Result};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    match Cli::parse().command {
-        Cmd::Caller { ringtone } => caller_mode(ringtone).await?,
-        Cmd::Peer { token } => peer_mode(token).await?,
+    let cli = Cli::parse();
+    match cli.command {
+        // The full-screen UI owns the terminal, so its logs go to a file
+        Cmd::Tui { .. } => logging::init_to_file(cli.log, &logging::log_path()?)?,
+        _ => logging::init(cli.log)?,
+    }
+    config::set_network_args(cli.network);
+    match cli.command {
+        Cmd::Caller { ringtone, call_waiting, call, forwarding } => {
+            caller_mode(ringtone, call_waiting, call, forwarding).await?
+        }
+        Cmd::Peer { token, call } => peer_mode(token, call).await?,
+        Cmd::Conference => conference_mode().await?,
+        Cmd::Channel { action } => channel_mode(action).await?,
+        Cmd::Station { tracks } => station_mode(tracks).await?,
+        Cmd::Tune { ticket } => tune_mode(ticket).await?,
+        Cmd::Voicemail { greeting, operator, max_length } => voicemail_mode(greeting, operator, max_length).await?,
+        Cmd::Tui { ringtone, call_waiting, call, forwarding } => {
+            tui_mode(ringtone, call_waiting, call, forwarding).await?
+        }
+        Cmd::Daemon { ringtone, call_waiting, socket, call, forwarding } => {
+            daemon_mode(ringtone, call_waiting, socket, call, forwarding).await?
+        }
+        Cmd::Ctl { socket, action } => ctl_mode(socket, action).await?,
+        Cmd::Contacts { action } => contacts_mode(action).await?,
+        Cmd::Config { action } => config_mode(action)?,
+        Cmd::Status { contact } => status_mode(contact).await?,
     }
     Ok(())
-}
\ No newline at end of file
+}
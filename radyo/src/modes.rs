@@ -1,91 +1,621 @@
 use anyhow::Result;
 use iroh::protocol::Router;
-use iroh::{Endpoint, NodeAddr, Watcher};
+use std::future::Future;
+use std::time::Duration;
+use iroh::{Endpoint, NodeId, Watcher};
 use iroh_base::ticket::NodeTicket;
-#[allow(unused_imports)]
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::path::{Path, PathBuf};
+use n0_future::join_all;
+use network::{wait_for_relay, RELAY_WAIT};
+use serde_json::json;
+use tracing::{debug, info, warn};
 
-use crate::call::{init_hangup_system, hangup, send_hangup_to_caller, CallManager};
+use crate::call::{call_span, hang_up_all, CallManager, CallOutcome, HANGUP_ACK_TIMEOUT};
+use crate::client::RadyoClient;
+use crate::call::new_call_id;
+use crate::channel::{join_channel, spawn_speaker, ChannelHub, ChannelTicket};
+use crate::cli::{spawn_channel_reader, spawn_command_reader, spawn_conference_reader, CallArgs, ChannelCmd, ConfigCmd, ContactsCmd, CtlCmd, ForwardArgs};
+use crate::config::{config_path, Config, DndSettings};
+use crate::dnd::DndRules;
+use crate::conference::{Conference, ConferenceCommand};
+use crate::contacts::{Contact, ContactBook};
+use crate::daemon::{default_socket_path, ControlSocket, DaemonClient};
+use crate::files::blobs_protocol;
+use crate::forwarding::{ForwardingRules, MAX_REDIRECTS};
+use crate::ivr::IvrCall;
+use crate::presence::{probe, probe_within, Presence};
 use crate::protocol::{RadyoProtocol, ALPN};
+use crate::station::{tune_in, Station};
+use crate::tui::run_tui;
+use crate::voice::{LocalAudio, VoiceControls};
+
+pub async fn caller_mode(ringtone: Option<String>, call_waiting: bool, call: CallArgs, forwarding: ForwardArgs) -> Result<()> {
+    let config = Config::load()?;
+    let ringtone = ringtone.unwrap_or_else(|| config.ringtone.clone());
+    info!("Starting persistent phone service with ringtone: {}", ringtone);
+    let (router, ticket) = start_phone(config, ringtone, call_waiting, call, forwarding).await?;
 
-pub async fn caller_mode(ringtone: String) -> Result<()> {
-    println!("📞 Starting persistent phone service with ringtone: {}", ringtone);
-    
-    // Store the ringtone preference globally
-    CallManager::set_ringtone(ringtone.clone())?;
-    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
-    let router = Router::builder(endpoint)
-        .accept(ALPN, RadyoProtocol)
-        .spawn();
-    let node_addr = router.endpoint().node_addr().initialized().await;
-    let ticket = NodeTicket::new(node_addr);
-    
     println!("📱 Your Contact Card (Node Ticket): {}", ticket);
-    println!("📞 Phone service is now online - waiting for calls...");
+    info!("Phone service is now online - waiting for calls...");
     println!("💡 Share your contact card with others so they can call you");
     println!("🔄 This service will handle multiple calls - each call is a separate session");
     println!("⏹️  Press Ctrl+C to shut down your phone service");
+    spawn_command_reader();
 
     tokio::signal::ctrl_c().await?;
-    println!("📞 Shutting down phone service...");
+    info!("Shutting down phone service...");
+    stop_phone(router).await?;
+    info!("Phone service stopped");
+    Ok(())
+}
+
+pub async fn daemon_mode(ringtone: Option<String>, call_waiting: bool, socket: Option<PathBuf>, call: CallArgs, forwarding: ForwardArgs) -> Result<()> {
+    let socket = ControlSocket::bind(&socket_path(socket)?).await?;
+    let config = Config::load()?;
+    let ringtone = ringtone.unwrap_or_else(|| config.ringtone.clone());
+    info!("Starting phone daemon with ringtone: {}", ringtone);
+    let (router, ticket) = start_phone(config, ringtone, call_waiting, call, forwarding).await?;
+
+    println!("📱 Your Contact Card (Node Ticket): {}", ticket);
+    println!("🔌 Control socket: {}", socket.path().display());
+    println!("💡 Drive the phone with `radyo ctl` or JSON-RPC on the socket");
+    println!("⏹️  Press Ctrl+C or send SIGINT to stop the daemon");
+
+    tokio::select! {
+        _ = socket.serve(router.endpoint().clone(), ticket) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    info!("Shutting down phone daemon...");
+    stop_phone(router).await?;
+    info!("Phone daemon stopped");
+    Ok(())
+}
+
+pub async fn tui_mode(ringtone: Option<String>, call_waiting: bool, call: CallArgs, forwarding: ForwardArgs) -> Result<()> {
+    let config = Config::load()?;
+    let ringtone = ringtone.unwrap_or_else(|| config.ringtone.clone());
+    let (router, ticket) = start_phone(config, ringtone, call_waiting, call, forwarding).await?;
+    let result = run_tui(router.endpoint().clone(), ticket).await;
+    info!("Shutting down phone service...");
+    stop_phone(router).await?;
+    result
+}
+
+// Bring the phone online: store the call settings and start taking calls
+async fn start_phone(mut config: Config, ringtone: String, call_waiting: bool, call: CallArgs, forwarding: ForwardArgs) -> Result<(Router, NodeTicket)> {
+    // Store the ringtone preference globally
+    CallManager::set_ringtone(ringtone)?;
+    CallManager::set_call_waiting(call_waiting);
+    apply_call_args(&mut config, call);
+    if let Some(secs) = forwarding.no_answer_after {
+        config.timeouts.no_answer = secs;
+    }
+    apply_config(&config)?;
+    let rules = forwarding_rules(&forwarding, &config)?;
+    if !rules.is_empty() {
+        print_forwarding(&rules);
+    }
+    CallManager::set_forwarding(rules)?;
+    let dnd = dnd_rules(&config.dnd)?;
+    print_dnd(&dnd);
+    CallManager::set_dnd_rules(dnd)?;
+    let endpoint = bind_phone_endpoint(&config).await?;
+    let router = Router::builder(endpoint.clone())
+        .accept(iroh_blobs::ALPN, blobs_protocol(&endpoint))
+        .accept(ALPN, RadyoProtocol::new(endpoint))
+        .spawn();
+    let ticket = node_ticket(router.endpoint()).await?;
+    Ok((router, ticket))
+}
+
+// Hang up whatever calls are still going, then go offline
+async fn stop_phone(router: Router) -> Result<()> {
+    if CallManager::is_call_in_progress() {
+        hang_up_all().await?;
+        let _ = tokio::time::timeout(HANGUP_ACK_TIMEOUT * 2, CallManager::wait_until_idle()).await;
+    }
     router.shutdown().await?;
-    println!("✅ Phone service stopped");
     Ok(())
 }
 
-pub async fn peer_mode(ticket: String) -> Result<()> {
-    println!("📞 Starting peer mode - calling: {}", ticket);
-    // Initialize hangup system
-    let mut hangup_rx = init_hangup_system();
-    
-    let node_id: NodeTicket = ticket
-        .parse()
-        .map_err(|_| anyhow::anyhow!("Invalid node Ticket format"))?;
-    let node_addr = NodeAddr::from(node_id);
-    println!("Dialing {ticket:?} ...");
-    // Create a client endpoint and connect to the peer using the same ALPN
-    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
-    let conn = endpoint.connect(node_addr, ALPN).await?;
-    println!("Connected. Opening bi-directional stream...");
-    let (mut send, mut recv) = conn.open_bi().await?;
-    
-    // Send incoming call signal to trigger caller's ringtone
-    println!("📞 Sending incoming call signal...");
-    send.write_all(b"INCOMING_CALL").await?;
-    println!("✅ Call initiated - caller should be ringing now");
-    
-    // Set up hangup monitoring
-    println!("⏳ Press Ctrl+C to hang up the call...");
-    println!("💡 You can also call hangup() programmatically");
-    
+fn socket_path(socket: Option<PathBuf>) -> Result<PathBuf> {
+    socket.map(Ok).unwrap_or_else(default_socket_path)
+}
+
+pub async fn ctl_mode(socket: Option<PathBuf>, action: CtlCmd) -> Result<()> {
+    let mut daemon = DaemonClient::connect(&socket_path(socket)?).await?;
+    let (method, params) = match action {
+        CtlCmd::Dial { target } => ("dial", json!({ "target": target })),
+        CtlCmd::Answer { call_id } => ("answer", json!({ "call_id": call_id })),
+        CtlCmd::Decline { call_id } => ("decline", json!({ "call_id": call_id })),
+        CtlCmd::Hangup { call_id } => ("hangup", json!({ "call_id": call_id })),
+        CtlCmd::Status => ("status", json!({})),
+        CtlCmd::Dnd { state } => ("dnd", json!({ "enabled": state.map(|state| state == "on") })),
+        CtlCmd::Events => {
+            daemon.request("subscribe", json!({})).await?;
+            loop {
+                println!("{}", daemon.next_event().await?);
+            }
+        }
+    };
+    println!("{}", daemon.request(method, params).await?);
+    Ok(())
+}
+
+// Store radyo.toml's settings globally for every call this process handles
+fn apply_config(config: &Config) -> Result<()> {
+    CallManager::set_hold_music(config.hold_music.clone())?;
+    CallManager::set_timeouts(config.timeouts)?;
+    CallManager::set_retry(config.retry)?;
+    CallManager::set_audio_settings(config.audio.clone())?;
+    CallManager::set_do_not_disturb(config.dnd.enabled);
+    Ok(())
+}
+
+// Let the in-call options on the command line override radyo.toml
+fn apply_call_args(config: &mut Config, call: CallArgs) {
+    if let Some(track) = call.hold_music {
+        config.hold_music = track;
+    }
+    if let Some(volume) = call.volume {
+        config.audio.volume = volume;
+    }
+    if let Some(secs) = call.dial_timeout {
+        config.timeouts.dial = secs;
+    }
+    if let Some(secs) = call.connect_timeout {
+        config.timeouts.connect = secs;
+    }
+    if let Some(retries) = call.retries {
+        config.retry.retries = retries;
+    }
+    if call.identity.is_some() {
+        config.identity = call.identity;
+    }
+    CallManager::set_stats_enabled(call.stats);
+    CallManager::set_recording_allowed(!call.no_recording);
+    CallManager::set_dtmf_tones(call.dtmf_tones);
+}
+
+// The phone's endpoint keeps the node ID from `identity`, if one is set
+async fn bind_phone_endpoint(config: &Config) -> Result<Endpoint> {
+    let mut builder = config.endpoint_builder()?;
+    if let Some(secret_key) = config.secret_key()? {
+        builder = builder.secret_key(secret_key);
+    }
+    Ok(builder.bind().await?)
+}
+
+// Settings for modes that don't take calls of their own
+async fn bind_endpoint() -> Result<Endpoint> {
+    let config = Config::load()?;
+    apply_config(&config)?;
+    Ok(config.endpoint_builder()?.bind().await?)
+}
+
+// Our ticket, with the home relay in it so nodes that can't reach us
+// directly still can
+async fn node_ticket(endpoint: &Endpoint) -> Result<NodeTicket> {
+    if Config::load()?.uses_relays() {
+        if wait_for_relay(endpoint).await {
+            debug!("Home relay: {:?}", endpoint.home_relay().get());
+        } else {
+            warn!("No relay answered within {:?} - the ticket only has direct addresses", RELAY_WAIT);
+        }
+    }
+    Ok(NodeTicket::new(endpoint.node_addr().initialized().await))
+}
+
+// Resolve the forwarding targets given on the command line
+fn forwarding_rules(args: &ForwardArgs, config: &Config) -> Result<ForwardingRules> {
+    let contacts = ContactBook::load()?;
+    let resolve = |target: &Option<String>| target.as_deref().map(|t| contacts.resolve(t)).transpose();
+    Ok(ForwardingRules {
+        always: resolve(&args.forward_always)?,
+        on_busy: resolve(&args.forward_busy)?,
+        on_no_answer: resolve(&args.forward_no_answer)?,
+        no_answer_timeout: config.timeouts.no_answer(),
+    })
+}
+
+fn print_forwarding(rules: &ForwardingRules) {
+    let contacts = ContactBook::load().unwrap_or_default();
+    let name = |ticket: &NodeTicket| contacts.display_name(ticket.node_addr().node_id);
+    if let Some(ticket) = &rules.always {
+        info!("Forwarding all calls to {}", name(ticket));
+    }
+    if let Some(ticket) = &rules.on_busy {
+        info!("Forwarding calls to {} when busy", name(ticket));
+    }
+    if let Some(ticket) = &rules.on_no_answer {
+        info!("Forwarding calls to {} after {:?} without answer", name(ticket), rules.no_answer_timeout);
+    }
+}
+
+// Resolve the voicemail target and favourites in radyo.toml's [dnd] section
+fn dnd_rules(settings: &DndSettings) -> Result<DndRules> {
+    let contacts = ContactBook::load()?;
+    Ok(DndRules {
+        schedule: settings.schedule.clone(),
+        voicemail: settings.voicemail.as_deref().map(|t| contacts.resolve(t)).transpose()?,
+        favourites: settings
+            .favourites
+            .iter()
+            .map(|f| contacts.resolve(f).map(|ticket| ticket.node_addr().node_id))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn print_dnd(rules: &DndRules) {
+    if CallManager::do_not_disturb() {
+        info!("Do not disturb is on");
+    }
+    for hours in &rules.schedule {
+        info!("Do not disturb from {} to {}", hours.from.format("%H:%M"), hours.to.format("%H:%M"));
+    }
+    let contacts = ContactBook::load().unwrap_or_default();
+    if let Some(ticket) = &rules.voicemail {
+        info!("Calls go to {} while do not disturb is on", contacts.display_name(ticket.node_addr().node_id));
+    }
+    for node_id in &rules.favourites {
+        info!("{} rings through do not disturb", contacts.display_name(*node_id));
+    }
+}
+
+// How long peer mode waits to hear whether the node would ring
+const PRE_DIAL_PROBE: Duration = Duration::from_secs(5);
+
+pub async fn peer_mode(target: String, call: CallArgs) -> Result<()> {
+    info!("Starting peer mode - calling: {}", target);
+    let mut config = Config::load()?;
+    apply_call_args(&mut config, call);
+    apply_config(&config)?;
+
+    let contacts = ContactBook::load()?;
+    let mut ticket = contacts.resolve(&target)?;
+    let client = RadyoClient::from_endpoint(bind_phone_endpoint(&config).await?);
+    spawn_command_reader();
+
+    // Follow call forwarding, but not round in circles
+    let mut dialed: Vec<NodeId> = Vec::new();
+    loop {
+        let node_id = ticket.node_addr().node_id;
+        dialed.push(node_id);
+        // Say so up front rather than ringing into the void
+        match probe_within(client.endpoint(), ticket.node_addr().clone(), PRE_DIAL_PROBE).await {
+            Ok(Presence::Offline) => anyhow::bail!("{} didn't answer within {:?} - it looks offline", contacts.display_name(node_id), PRE_DIAL_PROBE),
+            Ok(Presence::DoNotDisturb) => anyhow::bail!("{} doesn't want to be disturbed right now", contacts.display_name(node_id)),
+            Ok(presence) => info!("{} is {}", contacts.display_name(node_id), presence.describe()),
+            Err(e) => debug!("No presence from {}: {}", contacts.display_name(node_id), e),
+        }
+        let mut call = client.dial(ticket).await?;
+        info!("Press Ctrl+C to hang up the call...");
+        let outcome = tokio::select! {
+            outcome = &mut call => outcome?,
+            _ = tokio::signal::ctrl_c() => {
+                call_span(call.call_id()).in_scope(|| info!("Ctrl+C detected - initiating hangup..."));
+                call.hangup();
+                call.await?
+            }
+        };
+        match outcome {
+            CallOutcome::Redirected(next) => {
+                let next_node = next.node_addr().node_id;
+                if dialed.contains(&next_node) {
+                    warn!("Call forwarding loops back to {} - giving up", contacts.display_name(next_node));
+                    break;
+                }
+                if dialed.len() > MAX_REDIRECTS {
+                    warn!("Call was forwarded more than {} times - giving up", MAX_REDIRECTS);
+                    break;
+                }
+                info!("Redialing {}...", contacts.display_name(next_node));
+                ticket = next;
+            }
+            _ => break,
+        }
+    }
+
+    // Stay up while a call we were transferred into is still going
+    hang_up_on_ctrl_c(CallManager::wait_until_idle()).await?;
+    client.shutdown().await?;
+    Ok(())
+}
+
+// Wait for `calls` to finish, hanging them all up if the user presses Ctrl+C
+async fn hang_up_on_ctrl_c(calls: impl Future<Output = ()>) -> Result<()> {
+    tokio::pin!(calls);
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            println!("📞 Ctrl+C detected - initiating hangup...");
-            hangup().await?;
-            
-            // Send hangup and wait for acknowledgment
-            send_hangup_to_caller(&mut send).await?;
-            println!("⏳ Waiting for caller to acknowledge hangup...");
-            
-            // Wait for HANGUP_ACK from caller
-            let mut ack_buf = [0u8; 10]; // "HANGUP_ACK" length
-            match recv.read_exact(&mut ack_buf).await {
-                Ok(_) if &ack_buf == b"HANGUP_ACK" => {
-                    println!("✅ Caller acknowledged hangup - terminating cleanly");
+        _ = &mut calls => return Ok(()),
+        _ = tokio::signal::ctrl_c() => hang_up_all().await?,
+    }
+    calls.await;
+    Ok(())
+}
+
+pub async fn conference_mode() -> Result<()> {
+    info!("Starting conference host...");
+    let endpoint = bind_endpoint().await?;
+    let conference = Conference::new(endpoint.clone());
+    let router = Router::builder(endpoint)
+        .accept(ALPN, conference.clone())
+        .spawn();
+    let ticket = node_ticket(router.endpoint()).await?;
+
+    println!("📱 Conference ticket: {}", ticket);
+    println!("👥 Anyone who calls this ticket joins the conference");
+    println!("⏹️  Press Ctrl+C or 'q' to end the conference");
+    let (audio, mixer) = conference.start_mixer();
+    let mut commands = spawn_conference_reader();
+
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(ConferenceCommand::Add(ticket)) => conference.invite(ticket),
+                Some(ConferenceCommand::Remove(name)) => {
+                    if !conference.remove(&name) {
+                        warn!("No participant named {}", name);
+                    }
                 }
-                _ => {
-                    println!("⚠️ No acknowledgment received - terminating anyway");
+                Some(ConferenceCommand::Roster) => conference.print_roster(),
+                Some(ConferenceCommand::ToggleMute) => conference.toggle_mute(),
+                Some(ConferenceCommand::End) | None => break,
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    info!("Ending conference...");
+    conference.end().await;
+    mixer.abort();
+    audio.stop();
+    router.shutdown().await?;
+    info!("Conference ended");
+    Ok(())
+}
+
+pub async fn channel_mode(action: ChannelCmd) -> Result<()> {
+    let endpoint = bind_endpoint().await?;
+    let (audio, mic, speaker) = LocalAudio::start(VoiceControls::new(), new_call_id());
+    let speaker = spawn_speaker(speaker);
+
+    match action {
+        ChannelCmd::Create { name } => {
+            if name.contains(':') || name.contains(char::is_whitespace) {
+                anyhow::bail!("Channel names can't contain ':' or spaces");
+            }
+            let hub = ChannelHub::new(name.clone(), endpoint.clone(), speaker);
+            let router = Router::builder(endpoint)
+                .accept(ALPN, hub.clone())
+                .spawn();
+            let ticket = ChannelTicket { name, hub: node_ticket(router.endpoint()).await? };
+
+            println!("📻 Channel ticket: {}", ticket);
+            println!("📻 Share it so others can join with `radyo channel join <ticket>`");
+            let commands = spawn_channel_reader();
+            hub.run(mic, commands).await;
+            router.shutdown().await?;
+        }
+        ChannelCmd::Join { ticket } => {
+            let ticket: ChannelTicket = ticket.parse()?;
+            info!("Joining channel {}...", ticket.name);
+            let commands = spawn_channel_reader();
+            join_channel(&endpoint, ticket, mic, speaker, commands).await?;
+        }
+    }
+    audio.stop();
+    Ok(())
+}
+
+pub async fn station_mode(tracks: Vec<String>) -> Result<()> {
+    let playlist = playlist(&tracks)?;
+    info!("Starting radio station with {} track(s)...", playlist.len());
+    let endpoint = bind_endpoint().await?;
+    let station = Station::new(&endpoint);
+    let router = Router::builder(endpoint)
+        .accept(ALPN, station.clone())
+        .spawn();
+    let ticket = node_ticket(router.endpoint()).await?;
+
+    println!("📱 Station ticket: {}", ticket);
+    println!("📻 Share it so others can listen with `radyo tune <ticket>`");
+    println!("⏹️  Press Ctrl+C to go off air");
+
+    tokio::select! {
+        result = station.broadcast_playlist(playlist) => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    info!("Going off air...");
+    station.close().await;
+    router.shutdown().await?;
+    info!("Station stopped");
+    Ok(())
+}
+
+// Tracks are ringtone names from ringtons/ or paths to audio files
+fn playlist(tracks: &[String]) -> Result<Vec<PathBuf>> {
+    if tracks.is_empty() {
+        let mut all: Vec<PathBuf> = std::fs::read_dir("ringtons")?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mp3"))
+            .collect();
+        all.sort();
+        return Ok(all);
+    }
+    tracks.iter().map(|track| resolve_track(track)).collect()
+}
+
+fn resolve_track(track: &str) -> Result<PathBuf> {
+    let ringtone = PathBuf::from(format!("ringtons/{}.mp3", track));
+    if ringtone.exists() {
+        Ok(ringtone)
+    } else if Path::new(track).exists() {
+        Ok(PathBuf::from(track))
+    } else {
+        anyhow::bail!("No track named {}", track)
+    }
+}
+
+pub async fn tune_mode(target: String) -> Result<()> {
+    let ticket = ContactBook::load()?.resolve(&target)?;
+    info!("Tuning in to {}...", target);
+    let endpoint = bind_endpoint().await?;
+    let result = tune_in(&endpoint, ticket).await;
+    // Closing properly gets our HANGUP_ACK to the station before we exit
+    endpoint.close().await;
+    result
+}
+
+// How long callers have to press 0 for the operator after the greeting
+const OPERATOR_WAIT: Duration = Duration::from_secs(3);
+
+pub async fn voicemail_mode(greeting: Option<String>, operator: Option<String>, max_length: u64) -> Result<()> {
+    let greeting = greeting.as_deref().map(resolve_track).transpose()?;
+    let operator = operator.as_deref().map(|o| ContactBook::load()?.resolve(o)).transpose()?;
+    let max_length = Duration::from_secs(max_length);
+    info!("Starting voicemail...");
+
+    // Greet the caller, offer the operator on 0, then take a message
+    let answering_machine = move |mut call: IvrCall| {
+        let greeting = greeting.clone();
+        let operator = operator.clone();
+        async move {
+            call.answer().await?;
+            if let Some(greeting) = &greeting {
+                call.play(greeting).await?;
+            }
+            if let Some(operator) = &operator {
+                if call.read_digit(OPERATOR_WAIT).await? == Some('0') {
+                    return call.transfer(operator).await;
                 }
             }
+            call.record(max_length).await?;
+            call.hangup().await
+        }
+    };
+
+    let endpoint = bind_endpoint().await?;
+    let router = Router::builder(endpoint.clone())
+        .accept(ALPN, RadyoProtocol::with_handler(endpoint, answering_machine))
+        .spawn();
+    println!("📱 Voicemail ticket: {}", node_ticket(router.endpoint()).await?);
+    println!("📼 Callers hear the greeting and leave a message - press # to finish");
+    println!("⏹️  Press Ctrl+C to stop");
+
+    tokio::signal::ctrl_c().await?;
+    router.shutdown().await?;
+    info!("Voicemail stopped");
+    Ok(())
+}
+
+pub async fn contacts_mode(action: ContactsCmd) -> Result<()> {
+    let mut contacts = ContactBook::load()?;
+    match action {
+        ContactsCmd::Add { name, ticket, group } => {
+            let ticket: NodeTicket = ticket
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid node Ticket format"))?;
+            contacts.add(name.clone(), ticket);
+            if let (Some(group), Some(contact)) = (group, contacts.get_mut(&name)) {
+                contact.group = Some(group);
+            }
+            contacts.save()?;
+            println!("✅ Saved contact {}", name);
         }
-        _ = hangup_rx.recv() => {
-            println!("📞 Hangup signal received - terminating call...");
-            send_hangup_to_caller(&mut send).await?;
+        ContactsCmd::List { presence } => {
+            if contacts.contacts().is_empty() {
+                println!("📇 No contacts yet - add one with `radyo contacts add <name> <ticket>`");
+            }
+            let icons = if presence && !contacts.contacts().is_empty() {
+                probe_contacts(contacts.contacts()).await?
+            } else {
+                vec!["📇"; contacts.contacts().len()]
+            };
+            for (contact, icon) in contacts.contacts().iter().zip(icons) {
+                let mut line = format!("{} {} ({})", icon, contact.name, contact.node_id().fmt_short());
+                if let Some(group) = &contact.group {
+                    line.push_str(&format!(" [{}]", group));
+                }
+                if let Some(ringtone) = contacts.ringtone_for(contact.node_id()) {
+                    line.push_str(&format!(" 🎵 {}", ringtone));
+                }
+                println!("{}", line);
+            }
+        }
+        ContactsCmd::Group { name, group } => {
+            let contact = contacts.get_mut(&name).ok_or_else(|| anyhow::anyhow!("No contact named {}", name))?;
+            contact.group = group.clone();
+            contacts.save()?;
+            match group {
+                Some(group) => println!("✅ {} is in {}", name, group),
+                None => println!("✅ {} is in no group", name),
+            }
+        }
+        ContactsCmd::Ringtone { name, ringtone, group } => {
+            if let Some(track) = &ringtone {
+                if !Path::new(&format!("ringtons/{}.mp3", track)).exists() {
+                    anyhow::bail!("No ringtone named {} in ringtons/", track);
+                }
+            }
+            if group {
+                contacts.set_group_ringtone(&name, ringtone.clone());
+            } else {
+                let contact = contacts.get_mut(&name).ok_or_else(|| anyhow::anyhow!("No contact named {}", name))?;
+                contact.ringtone = ringtone.clone();
+            }
+            contacts.save()?;
+            match ringtone {
+                Some(ringtone) => println!("🎵 {} rings with {}", name, ringtone),
+                None => println!("🎵 {} rings with the default ringtone", name),
+            }
+        }
+        ContactsCmd::Remove { name } => {
+            if contacts.remove(&name) {
+                contacts.save()?;
+                println!("🗑️ Removed contact {}", name);
+            } else {
+                warn!("No contact named {}", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Everyone's presence icon, checked all at once
+async fn probe_contacts(contacts: &[Contact]) -> Result<Vec<&'static str>> {
+    let endpoint = bind_endpoint().await?;
+    let probes = contacts.iter().map(|contact| probe(&endpoint, contact.ticket.node_addr().clone()));
+    let icons = join_all(probes)
+        .await
+        .into_iter()
+        .map(|presence| presence.map(|p| p.icon()).unwrap_or("❔"))
+        .collect();
+    endpoint.close().await;
+    Ok(icons)
+}
+
+pub async fn status_mode(target: String) -> Result<()> {
+    let ticket = ContactBook::load()?.resolve(&target)?;
+    let endpoint = bind_endpoint().await?;
+    info!("Checking on {}...", target);
+    let presence = probe(&endpoint, ticket.node_addr().clone()).await;
+    endpoint.close().await;
+    let presence = presence?;
+    println!("{} {} is {}", presence.icon(), target, presence.describe());
+    Ok(())
+}
+
+pub fn config_mode(action: ConfigCmd) -> Result<()> {
+    match action {
+        ConfigCmd::Show => {
+            println!("# {}", config_path()?.display());
+            print!("{}", toml::to_string_pretty(&Config::load()?)?);
         }
-        _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
-            println!("📞 Call timed out");
+        ConfigCmd::Set { key, value } => {
+            Config::set(&key, &value)?;
+            println!("✅ Set {} = {}", key, value);
         }
     }
-    
     Ok(())
 }
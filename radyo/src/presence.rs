@@ -0,0 +1,123 @@
+use anyhow::Result;
+use iroh::endpoint::SendStream;
+use iroh::{Endpoint, NodeAddr, NodeId};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::call::{close_and_wait, CallManager};
+use crate::connect::DialError;
+use crate::protocol::{send_signal, Signal, SignalReader, ALPN};
+
+/// Whether a node would ring if you called it now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    Available,
+    /// Already on a call
+    Busy,
+    DoNotDisturb,
+    /// Didn't answer the probe in time
+    Offline,
+}
+
+impl Presence {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Presence::Available => "available",
+            Presence::Busy => "busy on another call",
+            Presence::DoNotDisturb => "not to be disturbed",
+            Presence::Offline => "offline",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Presence::Available => "🟢",
+            Presence::Busy => "🔴",
+            Presence::DoNotDisturb => "🔕",
+            Presence::Offline => "⚫",
+        }
+    }
+}
+
+impl fmt::Display for Presence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Presence::Available => "available",
+            Presence::Busy => "busy",
+            Presence::DoNotDisturb => "dnd",
+            Presence::Offline => "offline",
+        })
+    }
+}
+
+impl FromStr for Presence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "available" => Ok(Presence::Available),
+            "busy" => Ok(Presence::Busy),
+            "dnd" => Ok(Presence::DoNotDisturb),
+            "offline" => Ok(Presence::Offline),
+            _ => anyhow::bail!("Unknown presence {}", s),
+        }
+    }
+}
+
+/// Asks a node whether it would ring, without ringing it.
+///
+/// A node that can't be reached within the connect timeout is offline.
+pub async fn probe(endpoint: &Endpoint, node_addr: NodeAddr) -> Result<Presence> {
+    probe_within(endpoint, node_addr, CallManager::timeouts().connect()).await
+}
+
+/// Like [`probe`], but a node that takes longer than `wait` counts as offline.
+pub async fn probe_within(endpoint: &Endpoint, node_addr: NodeAddr, wait: Duration) -> Result<Presence> {
+    match tokio::time::timeout(wait, query(endpoint, node_addr)).await {
+        Ok(result) => result,
+        Err(_) => Ok(Presence::Offline),
+    }
+}
+
+async fn query(endpoint: &Endpoint, node_addr: NodeAddr) -> Result<Presence> {
+    let node_id = node_addr.node_id;
+    let conn = match endpoint.connect(node_addr, ALPN).await {
+        Ok(conn) => conn,
+        Err(e) => match DialError::from(e) {
+            e if e.is_retryable() => {
+                debug!("Couldn't reach {}: {}", node_id.fmt_short(), e);
+                return Ok(Presence::Offline);
+            }
+            e => return Err(e.into()),
+        },
+    };
+    let (mut send, recv) = conn.open_bi().await?;
+    send_signal(&mut send, &Signal::Presence).await?;
+    let reply = SignalReader::new(recv).next().await?;
+    conn.close(0u32.into(), b"done");
+    match reply {
+        Some(Signal::PresenceState(presence)) => Ok(presence),
+        _ => anyhow::bail!("{} doesn't report its presence - it may not be a phone", node_id.fmt_short()),
+    }
+}
+
+// What the phone tells `caller` - favourites see through do-not-disturb
+pub(crate) fn local_presence(caller: NodeId) -> Presence {
+    if CallManager::dnd_active() && !CallManager::dnd_rules().lets_through(caller) {
+        Presence::DoNotDisturb
+    } else if CallManager::is_call_in_progress() {
+        Presence::Busy
+    } else {
+        Presence::Available
+    }
+}
+
+// Answer a PRESENCE probe and hang up
+pub(crate) async fn answer_presence(mut send: SendStream, presence: Presence) -> Result<()> {
+    debug!("Reporting presence: {}", presence);
+    send_signal(&mut send, &Signal::PresenceState(presence)).await?;
+    close_and_wait(&mut send).await;
+    Ok(())
+}
@@ -1,22 +1,579 @@
 use anyhow::Result;
-use iroh::endpoint::Connection;
+use iroh::endpoint::{Connection, RecvStream, SendStream};
 use iroh::protocol::{AcceptError, ProtocolHandler};
-use std::future::Future;
-use crate::call::incoming_call_handler;
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::warn;
+use std::sync::Arc;
+use crate::call::{incoming_call_handler, HANGUP_ACK_TIMEOUT};
+use crate::ivr::{handle_bot_call, CallHandler};
+use crate::dtmf::is_dtmf_digit;
+use crate::files::FileOffer;
+use crate::presence::Presence;
 
-pub const ALPN: &[u8] = b"radyo/1.0";
+// Bumped from radyo/1.0 when signals became lines of text, so older nodes
+// refuse the connection instead of misreading it
+pub const ALPN: &[u8] = b"radyo/2";
 
-#[derive(Debug, Clone)]
-pub struct RadyoProtocol;
+/// Longest signal line a peer may send, newline included.
+pub const MAX_SIGNAL_LEN: usize = 8 * 1024;
+
+#[derive(Clone)]
+pub struct RadyoProtocol {
+    // Needed to look up which path (direct or relay) a call is using
+    endpoint: Endpoint,
+    // Answers calls in code instead of ringing the phone
+    handler: Option<Arc<dyn CallHandler>>,
+}
+
+impl fmt::Debug for RadyoProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RadyoProtocol")
+            .field("endpoint", &self.endpoint.node_id())
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+impl RadyoProtocol {
+    pub fn new(endpoint: Endpoint) -> Self {
+        Self { endpoint, handler: None }
+    }
+
+    /// Hands every incoming call to `handler` instead of ringing.
+    pub fn with_handler(endpoint: Endpoint, handler: impl CallHandler) -> Self {
+        Self { endpoint, handler: Some(Arc::new(handler)) }
+    }
+}
 
 impl ProtocolHandler for RadyoProtocol {
-    fn accept(&self, conn: Connection) -> impl Future<Output = Result<(), AcceptError>> + Send {
-        async move {
-            // Spawn each call handler concurrently to allow multiple calls
-            tokio::spawn(async move {
-                incoming_call_handler(conn).await;
-            });
-            Ok(())
+    async fn accept(&self, conn: Connection) -> Result<(), AcceptError> {
+        let endpoint = self.endpoint.clone();
+        let handler = self.handler.clone();
+        // Spawn each call handler concurrently to allow multiple calls
+        tokio::spawn(async move {
+            match handler {
+                Some(handler) => handle_bot_call(endpoint, conn, handler).await,
+                None => incoming_call_handler(endpoint, conn).await,
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Signalling messages exchanged on a call's control stream.
+///
+/// Each message is sent as a single line: an upper-case token followed by
+/// any space-separated arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    IncomingCall,
+    Busy,
+    /// Turned away because the callee doesn't want to be disturbed
+    DoNotDisturb,
+    Answer,
+    Hold,
+    Resume,
+    Hangup,
+    HangupAck,
+    Muted,
+    Unmuted,
+    /// Call forwarding: dial this node instead
+    Redirect(NodeTicket),
+    /// Call transfer: dial this node, then hang up this call
+    Transfer(NodeTicket),
+    /// Everyone in a conference or channel, host first
+    Roster(Vec<NodeId>),
+    /// Join a push-to-talk channel instead of placing a call
+    JoinChannel(String),
+    /// Push-to-talk floor control: ask to talk, and stop talking
+    FloorRequest,
+    FloorRelease,
+    FloorGranted,
+    /// This member is talking - also the answer to a refused FLOOR_REQUEST
+    FloorTaken(NodeId),
+    FloorFree,
+    /// Listen to a radio station instead of placing a call
+    TuneIn,
+    /// The station's current track and how far into it the broadcast is
+    NowPlaying { position: Duration, length: Duration, track: String },
+    Listeners(usize),
+    /// Opens a call's chat stream
+    ChatOpen,
+    /// A chat message and when the sender wrote it
+    Chat { id: u64, sent_at: SystemTime, text: String },
+    /// The chat message with this ID arrived
+    ChatReceipt(u64),
+    /// A file the other side can download from us over iroh-blobs
+    FileOffer(FileOffer),
+    /// The offered file with this hash was downloaded
+    FileReceived(iroh_blobs::Hash),
+    /// Asks to record the call - answered with RECORDING_ALLOWED or RECORDING_REFUSED
+    Recording,
+    RecordingAllowed,
+    RecordingRefused,
+    RecordingStopped,
+    /// A keypad digit: 0-9, *, # or A-D
+    Dtmf(char),
+    /// Asks whether the phone would ring, instead of placing a call
+    Presence,
+    PresenceState(Presence),
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::IncomingCall => f.write_str("INCOMING_CALL"),
+            Signal::Busy => f.write_str("BUSY"),
+            Signal::DoNotDisturb => f.write_str("DO_NOT_DISTURB"),
+            Signal::Answer => f.write_str("ANSWER"),
+            Signal::Hold => f.write_str("HOLD"),
+            Signal::Resume => f.write_str("RESUME"),
+            Signal::Hangup => f.write_str("HANGUP"),
+            Signal::HangupAck => f.write_str("HANGUP_ACK"),
+            Signal::Muted => f.write_str("MUTED"),
+            Signal::Unmuted => f.write_str("UNMUTED"),
+            Signal::Redirect(ticket) => write!(f, "REDIRECT {}", ticket),
+            Signal::Transfer(ticket) => write!(f, "TRANSFER {}", ticket),
+            Signal::Roster(nodes) => {
+                f.write_str("ROSTER")?;
+                for node in nodes {
+                    write!(f, " {}", node)?;
+                }
+                Ok(())
+            }
+            Signal::JoinChannel(name) => write!(f, "JOIN_CHANNEL {}", name),
+            Signal::FloorRequest => f.write_str("FLOOR_REQUEST"),
+            Signal::FloorRelease => f.write_str("FLOOR_RELEASE"),
+            Signal::FloorGranted => f.write_str("FLOOR_GRANTED"),
+            Signal::FloorTaken(node) => write!(f, "FLOOR_TAKEN {}", node),
+            Signal::FloorFree => f.write_str("FLOOR_FREE"),
+            Signal::TuneIn => f.write_str("TUNE_IN"),
+            Signal::NowPlaying { position, length, track } => {
+                write!(f, "NOW_PLAYING {} {} {}", position.as_millis(), length.as_millis(), track)
+            }
+            Signal::Listeners(count) => write!(f, "LISTENERS {}", count),
+            Signal::ChatOpen => f.write_str("CHAT_OPEN"),
+            Signal::Chat { id, sent_at, text } => {
+                let millis = sent_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                write!(f, "CHAT {} {} {}", id, millis, text.replace('\n', " "))
+            }
+            Signal::ChatReceipt(id) => write!(f, "CHAT_RECEIPT {}", id),
+            Signal::FileOffer(offer) => write!(f, "FILE_OFFER {} {} {}", offer.hash, offer.size, offer.name.replace('\n', " ")),
+            Signal::FileReceived(hash) => write!(f, "FILE_RECEIVED {}", hash),
+            Signal::Recording => f.write_str("RECORDING"),
+            Signal::RecordingAllowed => f.write_str("RECORDING_ALLOWED"),
+            Signal::RecordingRefused => f.write_str("RECORDING_REFUSED"),
+            Signal::RecordingStopped => f.write_str("RECORDING_STOPPED"),
+            Signal::Dtmf(digit) => write!(f, "DTMF {}", digit),
+            Signal::Presence => f.write_str("PRESENCE"),
+            Signal::PresenceState(presence) => write!(f, "PRESENCE_STATE {}", presence),
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let (token, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match token {
+            "INCOMING_CALL" => Ok(Signal::IncomingCall),
+            "BUSY" => Ok(Signal::Busy),
+            "DO_NOT_DISTURB" => Ok(Signal::DoNotDisturb),
+            "ANSWER" => Ok(Signal::Answer),
+            "HOLD" => Ok(Signal::Hold),
+            "RESUME" => Ok(Signal::Resume),
+            "HANGUP" => Ok(Signal::Hangup),
+            "HANGUP_ACK" => Ok(Signal::HangupAck),
+            "MUTED" => Ok(Signal::Muted),
+            "UNMUTED" => Ok(Signal::Unmuted),
+            "REDIRECT" => parse_ticket(args, line).map(Signal::Redirect),
+            "TRANSFER" => parse_ticket(args, line).map(Signal::Transfer),
+            "ROSTER" => args
+                .split_whitespace()
+                .map(|node| node.parse().map_err(|_| anyhow::anyhow!("Invalid node ID in signal: {}", line)))
+                .collect::<Result<_>>()
+                .map(Signal::Roster),
+            "JOIN_CHANNEL" if !args.trim().is_empty() => Ok(Signal::JoinChannel(args.trim().to_string())),
+            "FLOOR_REQUEST" => Ok(Signal::FloorRequest),
+            "FLOOR_RELEASE" => Ok(Signal::FloorRelease),
+            "FLOOR_GRANTED" => Ok(Signal::FloorGranted),
+            "FLOOR_TAKEN" => args
+                .trim()
+                .parse()
+                .map(Signal::FloorTaken)
+                .map_err(|_| anyhow::anyhow!("Invalid node ID in signal: {}", line)),
+            "FLOOR_FREE" => Ok(Signal::FloorFree),
+            "TUNE_IN" => Ok(Signal::TuneIn),
+            "NOW_PLAYING" => parse_now_playing(args).ok_or_else(|| anyhow::anyhow!("Invalid signal: {}", line)),
+            "LISTENERS" => args
+                .trim()
+                .parse()
+                .map(Signal::Listeners)
+                .map_err(|_| anyhow::anyhow!("Invalid signal: {}", line)),
+            "CHAT_OPEN" => Ok(Signal::ChatOpen),
+            "CHAT" => parse_chat(args).ok_or_else(|| anyhow::anyhow!("Invalid signal: {}", line)),
+            "CHAT_RECEIPT" => args
+                .trim()
+                .parse()
+                .map(Signal::ChatReceipt)
+                .map_err(|_| anyhow::anyhow!("Invalid signal: {}", line)),
+            "FILE_OFFER" => parse_file_offer(args).ok_or_else(|| anyhow::anyhow!("Invalid signal: {}", line)),
+            "FILE_RECEIVED" => parse_hash(args.trim())
+                .map(Signal::FileReceived)
+                .ok_or_else(|| anyhow::anyhow!("Invalid hash in signal: {}", line)),
+            "RECORDING" => Ok(Signal::Recording),
+            "RECORDING_ALLOWED" => Ok(Signal::RecordingAllowed),
+            "RECORDING_REFUSED" => Ok(Signal::RecordingRefused),
+            "RECORDING_STOPPED" => Ok(Signal::RecordingStopped),
+            "DTMF" => match args.trim().chars().collect::<Vec<_>>()[..] {
+                [digit] if is_dtmf_digit(digit) => Ok(Signal::Dtmf(digit.to_ascii_uppercase())),
+                _ => Err(anyhow::anyhow!("Invalid keypad digit in signal: {}", line)),
+            },
+            "PRESENCE" => Ok(Signal::Presence),
+            "PRESENCE_STATE" => args.parse().map(Signal::PresenceState),
+            _ => Err(anyhow::anyhow!("Unknown signal: {}", line)),
         }
     }
 }
+
+fn parse_ticket(args: &str, line: &str) -> Result<NodeTicket> {
+    args.trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid ticket in signal: {}", line))
+}
+
+// NOW_PLAYING <position ms> <length ms> <track name, may contain spaces>
+fn parse_now_playing(args: &str) -> Option<Signal> {
+    let mut parts = args.trim().splitn(3, ' ');
+    let position = Duration::from_millis(parts.next()?.parse().ok()?);
+    let length = Duration::from_millis(parts.next()?.parse().ok()?);
+    let track = parts.next().unwrap_or_default().to_string();
+    Some(Signal::NowPlaying { position, length, track })
+}
+
+// CHAT <id> <sent at, ms since the epoch> <text, may contain spaces>
+fn parse_chat(args: &str) -> Option<Signal> {
+    let mut parts = args.trim().splitn(3, ' ');
+    let id = parts.next()?.parse().ok()?;
+    let sent_at = UNIX_EPOCH + Duration::from_millis(parts.next()?.parse().ok()?);
+    let text = parts.next().unwrap_or_default().to_string();
+    Some(Signal::Chat { id, sent_at, text })
+}
+
+// FILE_OFFER <hash> <size in bytes> <file name, may contain spaces>
+fn parse_file_offer(args: &str) -> Option<Signal> {
+    let mut parts = args.trim().splitn(3, ' ');
+    let hash = parse_hash(parts.next()?)?;
+    let size = parts.next()?.parse().ok()?;
+    let name = parts.next().unwrap_or_default().to_string();
+    Some(Signal::FileOffer(FileOffer { hash, size, name }))
+}
+
+// Hash's FromStr panics on base32 that doesn't decode to exactly 32 bytes,
+// so only hand it the lengths a hash can have: 64 hex or 52 base32 chars
+fn parse_hash(s: &str) -> Option<iroh_blobs::Hash> {
+    match s.len() {
+        52 | 64 => s.parse().ok(),
+        _ => None,
+    }
+}
+
+pub async fn send_signal(send: &mut SendStream, signal: &Signal) -> Result<()> {
+    send.write_all(format!("{}\n", signal).as_bytes()).await?;
+    Ok(())
+}
+
+/// Sends HANGUP and waits up to [`HANGUP_ACK_TIMEOUT`] for the HANGUP_ACK.
+///
+/// Returns whether the other side acknowledged it; other signals that arrive
+/// in the meantime are dropped.
+pub async fn hangup_and_wait(send: &mut SendStream, signals: &mut SignalReader) -> Result<bool> {
+    send_signal(send, &Signal::Hangup).await?;
+    let wait_for_ack = async {
+        while let Ok(Some(signal)) = signals.next().await {
+            if signal == Signal::HangupAck {
+                return true;
+            }
+        }
+        false
+    };
+    Ok(tokio::time::timeout(HANGUP_ACK_TIMEOUT, wait_for_ack).await.unwrap_or(false))
+}
+
+/// Reads [`Signal`]s line by line from a call's control stream.
+pub struct SignalReader {
+    reader: BufReader<RecvStream>,
+    // The line read so far, kept here so `next` stays cancel safe
+    line: Vec<u8>,
+}
+
+impl SignalReader {
+    pub fn new(recv: RecvStream) -> Self {
+        Self {
+            reader: BufReader::new(recv),
+            line: Vec::new(),
+        }
+    }
+
+    /// Returns the next signal, or `None` once the remote closes the stream.
+    ///
+    /// Unknown signals are skipped so newer peers can talk to older ones.
+    /// A line longer than [`MAX_SIGNAL_LEN`] is an error. This is cancel safe
+    /// and can be used in `tokio::select!`.
+    pub async fn next(&mut self) -> Result<Option<Signal>> {
+        while let Some(line) = self.next_line().await? {
+            match line.parse() {
+                Ok(signal) => return Ok(Some(signal)),
+                Err(e) => warn!("Ignoring signal from peer: {}", e),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                if self.line.is_empty() {
+                    return Ok(None);
+                }
+                return Ok(Some(String::from_utf8(std::mem::take(&mut self.line))?));
+            }
+            let (chunk, end_of_line) = match available.iter().position(|&b| b == b'\n') {
+                Some(newline) => (&available[..newline], true),
+                None => (available, false),
+            };
+            if self.line.len() + chunk.len() >= MAX_SIGNAL_LEN {
+                anyhow::bail!("the other side sent a signal longer than {} bytes", MAX_SIGNAL_LEN);
+            }
+            self.line.extend_from_slice(chunk);
+            let used = chunk.len() + usize::from(end_of_line);
+            self.reader.consume(used);
+            if end_of_line {
+                return Ok(Some(String::from_utf8(std::mem::take(&mut self.line))?));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iroh::endpoint::RelayMode;
+    use iroh::{NodeAddr, RelayUrl, SecretKey, Watcher};
+
+    fn node_id() -> NodeId {
+        SecretKey::generate(rand::rngs::OsRng).public()
+    }
+
+    fn ticket() -> NodeTicket {
+        let relay: RelayUrl = "https://relay.example.com".parse().unwrap();
+        let addrs = ["192.168.1.20:4433".parse().unwrap(), "[fe80::1]:4433".parse().unwrap()];
+        NodeTicket::new(NodeAddr::from_parts(node_id(), Some(relay), addrs))
+    }
+
+    fn every_signal() -> Vec<Signal> {
+        vec![
+            Signal::IncomingCall,
+            Signal::Busy,
+            Signal::DoNotDisturb,
+            Signal::Answer,
+            Signal::Hold,
+            Signal::Resume,
+            Signal::Hangup,
+            Signal::HangupAck,
+            Signal::Muted,
+            Signal::Unmuted,
+            Signal::Redirect(ticket()),
+            Signal::Transfer(ticket()),
+            Signal::Roster(vec![]),
+            Signal::Roster(vec![node_id(), node_id(), node_id()]),
+            Signal::JoinChannel("ops".to_string()),
+            Signal::FloorRequest,
+            Signal::FloorRelease,
+            Signal::FloorGranted,
+            Signal::FloorTaken(node_id()),
+            Signal::FloorFree,
+            Signal::TuneIn,
+            Signal::NowPlaying {
+                position: Duration::from_millis(61_250),
+                length: Duration::from_millis(180_000),
+                track: "Lost Woods: the 10:30 remix".to_string(),
+            },
+            Signal::Listeners(12),
+            Signal::ChatOpen,
+            Signal::Chat {
+                id: 7,
+                sent_at: UNIX_EPOCH + Duration::from_millis(1_760_000_000_123),
+                text: "meet at 10:30 in room 2: bring the ticket".to_string(),
+            },
+            Signal::Chat { id: 8, sent_at: UNIX_EPOCH, text: String::new() },
+            Signal::ChatReceipt(7),
+            Signal::FileOffer(FileOffer {
+                hash: iroh_blobs::Hash::new(b"minutes"),
+                size: 2048,
+                name: "meeting notes: 18 Oct.txt".to_string(),
+            }),
+            Signal::FileReceived(iroh_blobs::Hash::new(b"minutes")),
+            Signal::Recording,
+            Signal::RecordingAllowed,
+            Signal::RecordingRefused,
+            Signal::RecordingStopped,
+            Signal::Dtmf('5'),
+            Signal::Dtmf('#'),
+            Signal::Dtmf('*'),
+            Signal::Dtmf('A'),
+            Signal::Presence,
+            Signal::PresenceState(Presence::Available),
+            Signal::PresenceState(Presence::Busy),
+            Signal::PresenceState(Presence::DoNotDisturb),
+            Signal::PresenceState(Presence::Offline),
+        ]
+    }
+
+    // No wildcard arm, so adding a variant fails to compile until every_signal covers it
+    fn is_covered(signal: &Signal) -> bool {
+        match signal {
+            Signal::IncomingCall
+            | Signal::Busy
+            | Signal::DoNotDisturb
+            | Signal::Answer
+            | Signal::Hold
+            | Signal::Resume
+            | Signal::Hangup
+            | Signal::HangupAck
+            | Signal::Muted
+            | Signal::Unmuted
+            | Signal::Redirect(_)
+            | Signal::Transfer(_)
+            | Signal::Roster(_)
+            | Signal::JoinChannel(_)
+            | Signal::FloorRequest
+            | Signal::FloorRelease
+            | Signal::FloorGranted
+            | Signal::FloorTaken(_)
+            | Signal::FloorFree
+            | Signal::TuneIn
+            | Signal::NowPlaying { .. }
+            | Signal::Listeners(_)
+            | Signal::ChatOpen
+            | Signal::Chat { .. }
+            | Signal::ChatReceipt(_)
+            | Signal::FileOffer(_)
+            | Signal::FileReceived(_)
+            | Signal::Recording
+            | Signal::RecordingAllowed
+            | Signal::RecordingRefused
+            | Signal::RecordingStopped
+            | Signal::Dtmf(_)
+            | Signal::Presence
+            | Signal::PresenceState(_) => true,
+        }
+    }
+
+    #[test]
+    fn every_signal_round_trips() {
+        for signal in every_signal() {
+            assert!(is_covered(&signal));
+            let line = signal.to_string();
+            assert!(!line.contains('\n'), "{line:?} spans lines");
+            assert!(line.len() < MAX_SIGNAL_LEN, "{line:?} is too long to send");
+            assert_eq!(line.parse::<Signal>().unwrap(), signal, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn newlines_in_text_become_spaces() {
+        let chat = Signal::Chat { id: 1, sent_at: UNIX_EPOCH, text: "two\nlines".to_string() };
+        assert_eq!(chat.to_string(), "CHAT 1 0 two lines");
+        let offer = FileOffer { hash: iroh_blobs::Hash::new(b"x"), size: 1, name: "a\nb".to_string() };
+        assert!(Signal::FileOffer(offer).to_string().ends_with(" 1 a b"));
+    }
+
+    #[test]
+    fn lowercase_keypad_letters_parse_as_uppercase() {
+        assert_eq!("DTMF b".parse::<Signal>().unwrap(), Signal::Dtmf('B'));
+    }
+
+    #[test]
+    fn malformed_signals_are_errors() {
+        for line in [
+            "",
+            "RING",
+            "hangup",
+            "REDIRECT",
+            "REDIRECT not-a-ticket",
+            "ROSTER abc",
+            "JOIN_CHANNEL",
+            "FLOOR_TAKEN",
+            "NOW_PLAYING 10",
+            "NOW_PLAYING x 10 song",
+            "LISTENERS many",
+            "CHAT 1",
+            "CHAT x 0 hi",
+            "CHAT_RECEIPT",
+            "FILE_OFFER nothash 1 a.txt",
+            "FILE_RECEIVED 123",
+            "DTMF",
+            "DTMF 55",
+            "DTMF x",
+            "PRESENCE_STATE away",
+        ] {
+            assert!(line.parse::<Signal>().is_err(), "{line:?} parsed");
+        }
+    }
+
+    // A control stream between two endpoints on this machine
+    async fn control_stream() -> Result<(Endpoint, Endpoint, SendStream, SignalReader)> {
+        let server = Endpoint::builder()
+            .relay_mode(RelayMode::Disabled)
+            .alpns(vec![ALPN.to_vec()])
+            .bind()
+            .await?;
+        let client = Endpoint::builder().relay_mode(RelayMode::Disabled).bind().await?;
+        let server_addr = server.node_addr().initialized().await;
+        let dial = async {
+            let conn = client.connect(server_addr, ALPN).await?;
+            let (mut send, _recv) = conn.open_bi().await?;
+            // The stream only reaches the other side once something is written
+            send_signal(&mut send, &Signal::IncomingCall).await?;
+            anyhow::Ok((conn, send))
+        };
+        let answer = async {
+            let conn = server.accept().await.expect("server endpoint closed").await?;
+            let (_send, recv) = conn.accept_bi().await?;
+            anyhow::Ok((conn, recv))
+        };
+        let ((_client_conn, send), (_server_conn, recv)) = tokio::try_join!(dial, answer)?;
+        let mut signals = SignalReader::new(recv);
+        assert_eq!(signals.next().await?, Some(Signal::IncomingCall));
+        Ok((server, client, send, signals))
+    }
+
+    #[tokio::test]
+    async fn reader_skips_unknown_signals() -> Result<()> {
+        let (_server, _client, mut send, mut signals) = control_stream().await?;
+        send.write_all(b"HOLD\nSOMETHING_NEW 1 2\nCHAT 3 0 hi: there\nRESUME").await?;
+        send.finish()?;
+        assert_eq!(signals.next().await?, Some(Signal::Hold));
+        assert_eq!(signals.next().await?, Some(Signal::Chat { id: 3, sent_at: UNIX_EPOCH, text: "hi: there".to_string() }));
+        // The last line ends with the stream instead of a newline
+        assert_eq!(signals.next().await?, Some(Signal::Resume));
+        assert_eq!(signals.next().await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reader_refuses_endless_lines() -> Result<()> {
+        let (_server, _client, mut send, mut signals) = control_stream().await?;
+        send.write_all(&vec![b'A'; MAX_SIGNAL_LEN * 2]).await?;
+        assert!(signals.next().await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use chrono::Local;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use crate::voice::{FRAME_SAMPLES, VOICE_SAMPLE_RATE};
+
+// How far one side may run ahead before it's written against silence,
+// e.g. while the other side is muted and sends nothing
+const MAX_QUEUED_SAMPLES: usize = FRAME_SAMPLES * 10;
+
+/// What ends up in a call recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingMode {
+    /// Both sides mixed into one mono track
+    Mixed,
+    /// A stereo file: you on the left, the other side on the right
+    SeparateTracks,
+}
+
+impl RecordingMode {
+    fn channels(self) -> u16 {
+        match self {
+            RecordingMode::Mixed => 1,
+            RecordingMode::SeparateTracks => 2,
+        }
+    }
+}
+
+/// Writes a call's audio to a WAV file named after the call.
+///
+/// Local and remote frames arrive independently, so each side is queued and
+/// written once the other side's audio for the same moment is there.
+pub struct CallRecorder {
+    path: PathBuf,
+    mode: RecordingMode,
+    writer: WavWriter<BufWriter<File>>,
+    local: VecDeque<i16>,
+    remote: VecDeque<i16>,
+}
+
+impl fmt::Debug for CallRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallRecorder").field("path", &self.path).field("mode", &self.mode).finish()
+    }
+}
+
+impl CallRecorder {
+    pub fn create(call_id: u128, mode: RecordingMode) -> Result<Self> {
+        let dir = recordings_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("call-{}-{}.wav", call_id, Local::now().format("%Y%m%d-%H%M%S")));
+        let spec = WavSpec {
+            channels: mode.channels(),
+            sample_rate: VOICE_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let writer = WavWriter::create(&path, spec)?;
+        Ok(Self { path, mode, writer, local: VecDeque::new(), remote: VecDeque::new() })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn record_local(&mut self, samples: &[i16]) -> Result<()> {
+        self.local.extend(samples);
+        self.write_ready()
+    }
+
+    pub fn record_remote(&mut self, samples: &[i16]) -> Result<()> {
+        self.remote.extend(samples);
+        self.write_ready()
+    }
+
+    // Write everything both sides have, plus whatever one side has queued too long
+    fn write_ready(&mut self) -> Result<()> {
+        let both = self.local.len().min(self.remote.len());
+        let ahead = self.local.len().max(self.remote.len());
+        self.write(if ahead > MAX_QUEUED_SAMPLES { ahead } else { both })
+    }
+
+    // Write `count` samples per side, filling in silence where one side is short
+    fn write(&mut self, count: usize) -> Result<()> {
+        for _ in 0..count {
+            let local = self.local.pop_front().unwrap_or(0);
+            let remote = self.remote.pop_front().unwrap_or(0);
+            match self.mode {
+                RecordingMode::Mixed => self.writer.write_sample(local.saturating_add(remote))?,
+                RecordingMode::SeparateTracks => {
+                    self.writer.write_sample(local)?;
+                    self.writer.write_sample(remote)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out what's still queued and closes the file.
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.write(self.local.len().max(self.remote.len()))?;
+        self.writer.finalize()?;
+        Ok(self.path)
+    }
+}
+
+// Recordings live next to the rest of radyo's data, e.g. ~/.local/share/radyo/recordings
+pub fn recordings_dir() -> Result<PathBuf> {
+    let dir = dirs_next::data_dir().ok_or_else(|| anyhow::anyhow!("no data directory"))?;
+    Ok(dir.join("radyo").join("recordings"))
+}
@@ -0,0 +1,150 @@
+use iroh::endpoint::{Connection, SendStream};
+use iroh::NodeId;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::call::HANGUP_ACK_TIMEOUT;
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+
+/// Everyone connected to a conference host, channel hub or station.
+///
+/// Each member's control stream runs in its own task (see [`run_member`]);
+/// the roster hands that task the signals to send. `T` is whatever else the
+/// host keeps per member.
+#[derive(Debug)]
+pub struct Roster<T> {
+    host: NodeId,
+    // Most members at once, not counting the host
+    limit: usize,
+    members: Arc<Mutex<Vec<Member<T>>>>,
+}
+
+impl<T> Clone for Roster<T> {
+    fn clone(&self) -> Self {
+        Self { host: self.host, limit: self.limit, members: self.members.clone() }
+    }
+}
+
+#[derive(Debug)]
+pub struct Member<T> {
+    pub node_id: NodeId,
+    pub conn: Connection,
+    // Signals for the member's control stream task
+    signals: mpsc::UnboundedSender<Signal>,
+    pub state: T,
+}
+
+impl<T> Roster<T> {
+    pub fn new(host: NodeId) -> Self {
+        Self::with_limit(host, usize::MAX)
+    }
+
+    /// A roster that turns members away once it has `limit` of them.
+    pub fn with_limit(host: NodeId, limit: usize) -> Self {
+        Self { host, limit, members: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// Everyone's node ID, host first.
+    pub fn node_ids(&self) -> Vec<NodeId> {
+        let members = self.members.lock().unwrap();
+        std::iter::once(self.host).chain(members.iter().map(|m| m.node_id)).collect()
+    }
+
+    /// How many members there are, not counting the host.
+    pub fn len(&self) -> usize {
+        self.members.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.limit
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Vec<Member<T>>> {
+        self.members.lock().unwrap()
+    }
+
+    /// Adds a member, returning the signals for their control stream task -
+    /// or `None` if the roster is full.
+    pub fn add(&self, node_id: NodeId, conn: Connection, state: T) -> Option<mpsc::UnboundedReceiver<Signal>> {
+        // Checked under the same lock as the push, so concurrent joins can't overfill it
+        let mut members = self.lock();
+        if members.len() >= self.limit {
+            return None;
+        }
+        let (signals, outgoing) = mpsc::unbounded_channel();
+        members.push(Member { node_id, conn, signals, state });
+        Some(outgoing)
+    }
+
+    pub fn remove(&self, node_id: NodeId) {
+        self.lock().retain(|m| m.node_id != node_id);
+    }
+
+    /// Sends `signal` to one member - false if they aren't on the roster.
+    pub fn send_to(&self, node_id: NodeId, signal: Signal) -> bool {
+        self.lock()
+            .iter()
+            .find(|m| m.node_id == node_id)
+            .is_some_and(|m| m.signals.send(signal).is_ok())
+    }
+
+    pub fn broadcast(&self, signal: Signal, except: Option<NodeId>) {
+        for member in self.lock().iter().filter(|m| Some(m.node_id) != except) {
+            let _ = member.signals.send(signal.clone());
+        }
+    }
+
+    /// Hangs up on every member and gives them a moment to leave.
+    pub async fn hang_up_all(&self) {
+        self.broadcast(Signal::Hangup, None);
+        let all_left = async {
+            while !self.is_empty() {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        };
+        let _ = tokio::time::timeout(HANGUP_ACK_TIMEOUT + Duration::from_secs(1), all_left).await;
+    }
+}
+
+/// Carries one member's control stream until either side hangs up.
+///
+/// Signals from `outgoing` are sent on, and a HANGUP among them waits for
+/// the member's HANGUP_ACK. Everything else the member sends goes to
+/// `on_signal`.
+pub async fn run_member(
+    mut send: SendStream,
+    mut signals: SignalReader,
+    mut outgoing: mpsc::UnboundedReceiver<Signal>,
+    mut on_signal: impl FnMut(Signal),
+) {
+    loop {
+        tokio::select! {
+            signal = signals.next() => match signal {
+                Ok(Some(Signal::Hangup)) => {
+                    let _ = send_signal(&mut send, &Signal::HangupAck).await;
+                    break;
+                }
+                Ok(Some(signal)) => on_signal(signal),
+                Ok(None) | Err(_) => break,
+            },
+            signal = outgoing.recv() => match signal {
+                Some(Signal::Hangup) => {
+                    let _ = hangup_and_wait(&mut send, &mut signals).await;
+                    break;
+                }
+                Some(signal) => {
+                    if send_signal(&mut send, &signal).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+        }
+    }
+    let _ = send.finish();
+}
@@ -0,0 +1,280 @@
+use anyhow::Result;
+use iroh::endpoint::{Connection, SendStream};
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use rodio::Source;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::call::new_call_id;
+use crate::connect::connect;
+use crate::contacts::ContactBook;
+use crate::protocol::{hangup_and_wait, send_signal, Signal, SignalReader};
+use crate::roster::{run_member, Roster};
+use crate::voice::{decode_frame, encode_frame, queue_playback, LocalAudio, Resampler, VoiceControls, FRAME_SAMPLES, VOICE_SAMPLE_RATE};
+
+// One frame goes out every 20 ms, the length of a frame
+const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+// How often the station prints what's on and who's listening
+const STATUS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The track on air and when it started.
+#[derive(Debug, Clone)]
+pub struct NowPlaying {
+    pub track: String,
+    pub started: Instant,
+    pub length: Duration,
+}
+
+/// A radio station broadcasting a playlist live to any number of listeners.
+///
+/// Listeners connect on the radyo ALPN with `TUNE_IN`. Everyone gets the same
+/// frames at the same time, so someone tuning in mid-song hears the live
+/// position, and `NOW_PLAYING` tells them how far in that is.
+#[derive(Debug, Clone)]
+pub struct Station {
+    station_id: u128,
+    listeners: Roster<()>,
+    // Loaded once when the station starts, to name listeners in the log
+    contacts: Arc<ContactBook>,
+    now_playing: Arc<Mutex<Option<NowPlaying>>>,
+}
+
+impl ProtocolHandler for Station {
+    async fn accept(&self, conn: Connection) -> Result<(), AcceptError> {
+        let station = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = station.accept_listener(conn).await {
+                warn!(station_id = station.station_id, "Couldn't add listener: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Station {
+    pub fn new(endpoint: &Endpoint) -> Self {
+        Self {
+            station_id: new_call_id(),
+            listeners: Roster::new(endpoint.node_id()),
+            contacts: Arc::new(ContactBook::load_or_default()),
+            now_playing: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn listener_count(&self) -> usize {
+        self.listeners.len()
+    }
+
+    pub fn now_playing(&self) -> Option<NowPlaying> {
+        self.now_playing.lock().unwrap().clone()
+    }
+
+    fn now_playing_signal(&self) -> Option<Signal> {
+        self.now_playing().map(|now| Signal::NowPlaying {
+            position: now.started.elapsed().min(now.length),
+            length: now.length,
+            track: now.track,
+        })
+    }
+
+    pub fn print_status(&self) {
+        let listeners = self.listener_count();
+        match self.now_playing() {
+            Some(now) => info!(
+                station_id = self.station_id,
+                "Now playing: {} [{} / {}] - {} listener(s)",
+                now.track, format_time(now.started.elapsed()), format_time(now.length), listeners
+            ),
+            None => info!(station_id = self.station_id, "Off air - {} listener(s)", listeners),
+        }
+    }
+
+    async fn accept_listener(&self, conn: Connection) -> Result<()> {
+        let (mut send, recv) = conn.accept_bi().await?;
+        let mut signals = SignalReader::new(recv);
+        if signals.next().await? != Some(Signal::TuneIn) {
+            return Ok(());
+        }
+        send_signal(&mut send, &Signal::Answer).await?;
+        if let Some(signal) = self.now_playing_signal() {
+            send_signal(&mut send, &signal).await?;
+        }
+
+        let node_id = conn.remote_node_id()?;
+        let outgoing = self.listeners.add(node_id, conn, ()).expect("stations take any number of listeners");
+        info!(station_id = self.station_id, "{} tuned in", self.contacts.display_name(node_id));
+        self.broadcast(Signal::Listeners(self.listener_count()));
+        self.print_status();
+
+        tokio::spawn(self.clone().run_listener(node_id, send, signals, outgoing));
+        Ok(())
+    }
+
+    // Carries one listener's control stream until they tune out
+    async fn run_listener(
+        self,
+        node_id: NodeId,
+        send: SendStream,
+        signals: SignalReader,
+        outgoing: mpsc::UnboundedReceiver<Signal>,
+    ) {
+        // Listeners have nothing to say besides HANGUP
+        run_member(send, signals, outgoing, |_| {}).await;
+        self.listeners.remove(node_id);
+        info!(station_id = self.station_id, "{} tuned out", self.contacts.display_name(node_id));
+        self.broadcast(Signal::Listeners(self.listener_count()));
+    }
+
+    fn broadcast(&self, signal: Signal) {
+        self.listeners.broadcast(signal, None);
+    }
+
+    /// Plays the playlist on a loop, decoding each track ahead of time.
+    pub async fn broadcast_playlist(&self, playlist: Vec<PathBuf>) -> Result<()> {
+        if playlist.is_empty() {
+            anyhow::bail!("The playlist is empty");
+        }
+        let decode = |path: PathBuf| tokio::task::spawn_blocking(move || decode_track(&path));
+        let mut index = 0;
+        let mut next = decode(playlist[index].clone());
+        let mut status = Instant::now();
+        loop {
+            let track = track_name(&playlist[index]);
+            let samples = match next.await? {
+                Ok(samples) => samples,
+                Err(e) => {
+                    warn!(station_id = self.station_id, "Skipping {}: {}", track, e);
+                    Vec::new()
+                }
+            };
+            index = (index + 1) % playlist.len();
+            next = decode(playlist[index].clone());
+            if samples.is_empty() {
+                continue;
+            }
+
+            let length = Duration::from_secs_f64(samples.len() as f64 / VOICE_SAMPLE_RATE as f64);
+            *self.now_playing.lock().unwrap() = Some(NowPlaying { track, started: Instant::now(), length });
+            if let Some(signal) = self.now_playing_signal() {
+                self.broadcast(signal);
+            }
+            self.print_status();
+
+            let mut tick = tokio::time::interval(FRAME_INTERVAL);
+            for (seq, frame) in samples.chunks(FRAME_SAMPLES).enumerate() {
+                tick.tick().await;
+                let datagram = encode_frame(seq as u32, frame);
+                for listener in self.listeners.lock().iter() {
+                    let _ = listener.conn.send_datagram(datagram.clone());
+                }
+                if status.elapsed() >= STATUS_INTERVAL {
+                    self.print_status();
+                    status = Instant::now();
+                }
+            }
+        }
+    }
+
+    /// Hangs up on every listener and waits for them to tune out.
+    pub async fn close(&self) {
+        self.listeners.hang_up_all().await;
+    }
+}
+
+// A track's name is its file name without the extension
+pub fn track_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn format_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Decodes an audio file into the mono 16 kHz samples calls use.
+pub fn decode_track(path: &Path) -> Result<Vec<i16>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let decoder = rodio::Decoder::new(file)?;
+    let channels = decoder.channels().max(1) as usize;
+    let mut resampler = Resampler::new(decoder.sample_rate(), VOICE_SAMPLE_RATE);
+
+    let interleaved: Vec<f32> = decoder.convert_samples::<f32>().collect();
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    let mut resampled = Vec::with_capacity(mono.len() * VOICE_SAMPLE_RATE as usize / 44_100 + 1);
+    resampler.process(&mono, &mut resampled);
+    Ok(resampled
+        .into_iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect())
+}
+
+/// Tunes in to a station and plays it until the user stops or the station closes.
+pub async fn tune_in(endpoint: &Endpoint, ticket: NodeTicket) -> Result<()> {
+    let station = ticket.node_addr().node_id.fmt_short();
+    let conn = connect(endpoint, ticket.node_addr().clone()).await?;
+    let (mut send, recv) = conn.open_bi().await?;
+    let mut signals = SignalReader::new(recv);
+    send_signal(&mut send, &Signal::TuneIn).await?;
+    match signals.next().await? {
+        Some(Signal::Answer) => info!("Tuned in to {} - press Ctrl+C to stop", station),
+        _ => anyhow::bail!("{} isn't a radio station", station),
+    }
+
+    let (audio, mut speaker) = LocalAudio::speaker(VoiceControls::new(), new_call_id());
+    let receiver = {
+        let conn = conn.clone();
+        tokio::spawn(async move {
+            while let Ok(datagram) = conn.read_datagram().await {
+                if let Some((_seq, samples)) = decode_frame(&datagram) {
+                    queue_playback(&mut speaker, &samples);
+                }
+            }
+        })
+    };
+
+    let mut hang_up = false;
+    loop {
+        tokio::select! {
+            signal = signals.next() => match signal {
+                Ok(Some(Signal::NowPlaying { position, length, track })) => {
+                    info!("Now playing: {} [{} / {}]", track, format_time(position), format_time(length));
+                }
+                Ok(Some(Signal::Listeners(count))) => info!("{} listener(s) tuned in", count),
+                Ok(Some(Signal::Hangup)) => {
+                    let _ = send_signal(&mut send, &Signal::HangupAck).await;
+                    info!("{} went off air", station);
+                    break;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) | Err(_) => {
+                    info!("Lost the station");
+                    break;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => {
+                hang_up = true;
+                break;
+            }
+        }
+    }
+
+    receiver.abort();
+    audio.stop();
+    if hang_up {
+        hangup_and_wait(&mut send, &mut signals).await?;
+        info!("Tuned out");
+    }
+    let _ = send.finish();
+    Ok(())
+}
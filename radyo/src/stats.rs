@@ -0,0 +1,135 @@
+use iroh::endpoint::{Connection, ConnectionType};
+use iroh::{Endpoint, NodeId, Watcher};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::{info, Instrument};
+
+use crate::call::{emit_event, CallEvent, CallManager};
+
+// How often live statistics are printed while a call is running
+pub const STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+// How often calls report their stats as a CallEvent, e.g. for a live display
+pub const LIVE_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Which network path a call is currently using.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathKind {
+    Direct,
+    Relay,
+    Mixed,
+    Unknown,
+}
+
+impl PathKind {
+    pub fn from_conn_type(conn_type: &ConnectionType) -> Self {
+        match conn_type {
+            ConnectionType::Direct(_) => PathKind::Direct,
+            ConnectionType::Relay(_) => PathKind::Relay,
+            ConnectionType::Mixed(_, _) => PathKind::Mixed,
+            ConnectionType::None => PathKind::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for PathKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PathKind::Direct => "direct",
+            PathKind::Relay => "relay",
+            PathKind::Mixed => "mixed",
+            PathKind::Unknown => "unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A snapshot of connection quality for one call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallStats {
+    pub rtt: Duration,
+    pub path: PathKind,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub packets_sent: u64,
+    pub packets_lost: u64,
+}
+
+impl CallStats {
+    /// Reads the current stats of `conn` and asks the endpoint which path it uses.
+    pub fn sample(endpoint: &Endpoint, conn: &Connection) -> Self {
+        let stats = conn.stats();
+        let path = conn
+            .remote_node_id()
+            .ok()
+            .map(|node_id| path_kind(endpoint, node_id))
+            .unwrap_or(PathKind::Unknown);
+
+        Self {
+            rtt: conn.rtt(),
+            path,
+            bytes_sent: stats.udp_tx.bytes,
+            bytes_received: stats.udp_rx.bytes,
+            packets_sent: stats.path.sent_packets,
+            packets_lost: stats.path.lost_packets,
+        }
+    }
+
+    pub fn loss_percent(&self) -> f64 {
+        if self.packets_sent == 0 {
+            return 0.0;
+        }
+        self.packets_lost as f64 * 100.0 / self.packets_sent as f64
+    }
+}
+
+impl fmt::Display for CallStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rtt={:?} path={} sent={}B recv={}B loss={:.1}%",
+            self.rtt,
+            self.path,
+            self.bytes_sent,
+            self.bytes_received,
+            self.loss_percent()
+        )
+    }
+}
+
+pub fn path_kind(endpoint: &Endpoint, node_id: NodeId) -> PathKind {
+    endpoint
+        .conn_type(node_id)
+        .map(|mut watcher| PathKind::from_conn_type(&watcher.get()))
+        .unwrap_or(PathKind::Unknown)
+}
+
+// Report stats every LIVE_STATS_INTERVAL, and print a stats line every STATS_INTERVAL
+// with --stats, until the task is aborted or the connection closes
+pub fn spawn_stats_logger(endpoint: Endpoint, conn: Connection, call_id: u128) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LIVE_STATS_INTERVAL);
+        let mut last_printed = Instant::now();
+        interval.tick().await; // First tick completes immediately
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let stats = CallStats::sample(&endpoint, &conn);
+                    emit_event(call_id, CallEvent::Stats(stats));
+                    if CallManager::stats_enabled() && last_printed.elapsed() >= STATS_INTERVAL {
+                        info!(
+                            rtt_ms = stats.rtt.as_millis() as u64,
+                            path = %stats.path,
+                            bytes_sent = stats.bytes_sent,
+                            bytes_received = stats.bytes_received,
+                            loss_percent = stats.loss_percent(),
+                            "Call stats"
+                        );
+                        last_printed = Instant::now();
+                    }
+                }
+                _ = conn.closed() => break,
+            }
+        }
+    }.in_current_span())
+}
@@ -0,0 +1,509 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use iroh::{Endpoint, NodeId};
+use iroh_base::ticket::NodeTicket;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, LineGauge, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::call::{answer_call, dial, send_command, send_command_to, subscribe_events, CallCommand, CallDirection, CallEvent, CallManager, CallPhase};
+use crate::contacts::{Contact, ContactBook};
+use crate::logging::log_path;
+use crate::presence::{probe, Presence};
+use crate::stats::CallStats;
+
+// Redraw at least this often, so call timers keep running
+const TICK: Duration = Duration::from_millis(250);
+
+// How many finished calls the history pane lists
+const HISTORY_LEN: usize = 50;
+
+// How often the contact list checks who is available
+const PRESENCE_REFRESH: Duration = Duration::from_secs(30);
+
+/// What the TUI knows about one call, built up from [`CallEvent`]s.
+struct CallView {
+    call_id: u128,
+    remote: Option<String>,
+    direction: Option<CallDirection>,
+    phase: CallPhase,
+    answered_at: Option<Instant>,
+    stats: Option<CallStats>,
+    mic: u8,
+    speaker: u8,
+    muted: bool,
+}
+
+impl CallView {
+    fn new(call_id: u128, phase: CallPhase) -> Self {
+        Self { call_id, remote: None, direction: None, phase, answered_at: None, stats: None, mic: 0, speaker: 0, muted: false }
+    }
+
+    fn name(&self) -> String {
+        self.remote.clone().unwrap_or_else(|| format!("CALL-{}", self.call_id))
+    }
+
+    fn is_ringing_in(&self) -> bool {
+        self.direction == Some(CallDirection::Incoming) && matches!(self.phase, CallPhase::Ringing | CallPhase::Waiting)
+    }
+
+    fn on_event(&mut self, event: CallEvent, book: &ContactBook) {
+        match event {
+            CallEvent::Started { remote, direction } => {
+                self.remote = Some(book.display_name(remote));
+                self.direction = Some(direction);
+            }
+            CallEvent::PhaseChanged(phase) => {
+                if phase == CallPhase::Active && self.answered_at.is_none() {
+                    self.answered_at = Some(Instant::now());
+                }
+                if !matches!(phase, CallPhase::Active) {
+                    (self.mic, self.speaker) = (0, 0);
+                }
+                self.phase = phase;
+            }
+            CallEvent::Stats(stats) => self.stats = Some(stats),
+            CallEvent::Levels { mic, speaker } => (self.mic, self.speaker) = (mic, speaker),
+            CallEvent::Muted(muted) => self.muted = muted,
+            CallEvent::Dtmf(_) => {}
+        }
+    }
+}
+
+// Where the TUI is in taking keys
+enum Input {
+    Normal,
+    /// Typing a contact name or ticket to dial
+    Dial(String),
+}
+
+struct App {
+    endpoint: Endpoint,
+    ticket: NodeTicket,
+    // Loaded once, so redraws don't read contacts.toml
+    book: ContactBook,
+    // Each contact's presence, once it has been checked
+    presence: HashMap<NodeId, Presence>,
+    selected: ListState,
+    calls: Vec<CallView>,
+    input: Input,
+    status: String,
+    // Results of dials running in the background
+    dial_tx: mpsc::UnboundedSender<String>,
+    quit: bool,
+}
+
+impl App {
+    fn focused(&self) -> Option<&CallView> {
+        let focused = CallManager::focused_call()?;
+        self.calls.iter().find(|call| call.call_id == focused)
+    }
+
+    fn ringing(&self) -> Option<&CallView> {
+        self.calls.iter().find(|call| call.is_ringing_in())
+    }
+
+    fn on_call_event(&mut self, call_id: u128, event: CallEvent) {
+        if !self.calls.iter().any(|call| call.call_id == call_id) {
+            self.calls.push(CallView::new(call_id, CallPhase::Ringing));
+        }
+        if let Some(call) = self.calls.iter_mut().find(|call| call.call_id == call_id) {
+            call.on_event(event, &self.book);
+        }
+    }
+
+    // Drop calls that have freed their line
+    fn sync_calls(&mut self) {
+        let active = CallManager::active_calls();
+        self.calls.retain(|call| active.iter().any(|(id, _)| *id == call.call_id));
+        for (call_id, phase) in active {
+            if !self.calls.iter().any(|call| call.call_id == call_id) {
+                self.calls.push(CallView::new(call_id, phase));
+            }
+        }
+    }
+
+    fn on_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        match &mut self.input {
+            Input::Dial(target) => match key.code {
+                KeyCode::Enter => {
+                    let target = std::mem::take(target);
+                    self.input = Input::Normal;
+                    if !target.trim().is_empty() {
+                        self.dial(target.trim().to_string());
+                    }
+                }
+                KeyCode::Esc => self.input = Input::Normal,
+                KeyCode::Backspace => {
+                    target.pop();
+                }
+                KeyCode::Char(c) => target.push(c),
+                _ => {}
+            },
+            Input::Normal => self.on_normal_key(key.code),
+        }
+    }
+
+    fn on_normal_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.selected.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.selected.select_next(),
+            KeyCode::Enter => {
+                if let Some(contact) = self.selected.selected().and_then(|i| self.book.contacts().get(i)) {
+                    self.dial(contact.name.clone());
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Char(':') => self.input = Input::Dial(String::new()),
+            KeyCode::Char('a') => match self.ringing().map(|call| call.call_id) {
+                Some(call_id) => {
+                    answer_call(call_id);
+                }
+                None => self.status = "No call to answer".to_string(),
+            },
+            KeyCode::Char('n') => match self.ringing().map(|call| call.call_id) {
+                Some(call_id) => {
+                    send_command_to(call_id, CallCommand::Decline);
+                }
+                None => self.status = "No call to decline".to_string(),
+            },
+            KeyCode::Char('e') => self.command(CallCommand::Hangup),
+            KeyCode::Char('h') => match self.focused().map(|call| call.phase) {
+                Some(CallPhase::OnHold { by_us: true }) => self.command(CallCommand::Resume),
+                _ => self.command(CallCommand::Hold),
+            },
+            // The label follows the call's Muted events, not the key press
+            KeyCode::Char('m') => self.command(CallCommand::ToggleMute),
+            KeyCode::Char('s') => self.command(CallCommand::Switch),
+            KeyCode::Char('+') => self.command(CallCommand::VolumeUp),
+            KeyCode::Char('-') => self.command(CallCommand::VolumeDown),
+            KeyCode::Char('z') => {
+                let enabled = CallManager::toggle_do_not_disturb();
+                self.status = format!("Do not disturb {}", if enabled { "on" } else { "off" });
+            }
+            _ => {}
+        }
+    }
+
+    fn command(&mut self, command: CallCommand) {
+        if !send_command(command.clone()) {
+            self.status = format!("No call to {}", command);
+        }
+    }
+
+    // Resolve and dial `target` in the background; the call shows up through its events
+    fn dial(&mut self, target: String) {
+        self.status = format!("Dialing {}...", target);
+        let endpoint = self.endpoint.clone();
+        let results = self.dial_tx.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let ticket = ContactBook::load()?.resolve(&target)?;
+                dial(&endpoint, ticket.node_addr().clone()).await
+            }
+            .await;
+            let status = match result {
+                Ok(call) => {
+                    call.spawn();
+                    format!("Ringing {}", target)
+                }
+                Err(e) => format!("Couldn't call {}: {}", target, e),
+            };
+            let _ = results.send(status);
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, main, prompt, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [contacts, right] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(main);
+        let [calls, history] = Layout::vertical([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(right);
+
+        let mut title = Line::from(vec![Span::styled(
+            " radyo ",
+            Style::new().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )]);
+        if CallManager::dnd_active() {
+            title.push_span(Span::styled(" 🔕 DND ", Style::new().fg(Color::Black).bg(Color::Yellow)));
+        }
+        title.push_span(Span::raw(format!(" {}", self.ticket)));
+        frame.render_widget(Paragraph::new(title), header);
+        self.draw_contacts(frame, contacts);
+        self.draw_calls(frame, calls);
+        draw_history(frame, history, &self.book);
+        self.draw_prompt(frame, prompt);
+
+        let keys = "↑↓ select  Enter call  d dial  a answer  n decline  e end  h hold  m mute  s switch  +/- volume  z do not disturb  q quit";
+        frame.render_widget(Paragraph::new(keys).style(Style::new().fg(Color::DarkGray)), help);
+
+        if let Some(call) = self.ringing() {
+            draw_incoming(frame, call);
+        }
+    }
+
+    fn draw_contacts(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .book
+            .contacts()
+            .iter()
+            .map(|contact| ListItem::new(Line::from(vec![
+                Span::raw(format!("{} ", self.presence.get(&contact.node_id()).map_or("  ", |p| p.icon()))),
+                Span::raw(contact.name.clone()),
+                Span::styled(format!(" {}", contact.node_id().fmt_short()), Style::new().fg(Color::DarkGray)),
+            ])))
+            .collect();
+        let title = if items.is_empty() { " Contacts (none yet) " } else { " Contacts " };
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(list, area, &mut self.selected);
+    }
+
+    fn draw_calls(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Calls ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if self.calls.is_empty() {
+            frame.render_widget(Paragraph::new("No calls - pick a contact and press Enter, or d to dial"), inner);
+            return;
+        }
+
+        let focused = CallManager::focused_call();
+        let rows = Layout::vertical(self.calls.iter().map(|_| Constraint::Length(5))).split(inner);
+        for (call, row) in self.calls.iter().zip(rows.iter()) {
+            let [heading, quality, mic, speaker, _] = Layout::vertical([Constraint::Length(1); 5]).areas(*row);
+            let timer = call.answered_at.map(|at| format_duration(at.elapsed())).unwrap_or_else(|| "--:--".to_string());
+            let marker = if focused == Some(call.call_id) { "▶ " } else { "  " };
+            let mut spans = vec![
+                Span::raw(marker),
+                Span::styled(call.name(), Style::new().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  {}  ", call.phase)),
+                Span::styled(timer, Style::new().fg(Color::Cyan)),
+            ];
+            if call.muted {
+                spans.push(Span::styled("  muted", Style::new().fg(Color::Red)));
+            }
+            frame.render_widget(Paragraph::new(Line::from(spans)), heading);
+
+            let quality_text = match &call.stats {
+                Some(stats) => format!(
+                    "  rtt {} ms · {} · loss {:.1}% · ↑{} ↓{}",
+                    stats.rtt.as_millis(),
+                    stats.path,
+                    stats.loss_percent(),
+                    crate::files::format_size(stats.bytes_sent),
+                    crate::files::format_size(stats.bytes_received),
+                ),
+                None => "  waiting for stats...".to_string(),
+            };
+            frame.render_widget(Paragraph::new(quality_text).style(Style::new().fg(Color::DarkGray)), quality);
+            frame.render_widget(level_meter("mic", call.mic), mic);
+            frame.render_widget(level_meter("spk", call.speaker), speaker);
+        }
+    }
+
+    fn draw_prompt(&self, frame: &mut Frame, area: Rect) {
+        match &self.input {
+            Input::Dial(target) => {
+                let prompt = Paragraph::new(target.as_str()).block(Block::bordered().title(" Dial (contact or ticket, Enter to call, Esc to cancel) "));
+                frame.render_widget(prompt, area);
+                frame.set_cursor_position((area.x + 1 + target.chars().count() as u16, area.y + 1));
+            }
+            Input::Normal => {
+                frame.render_widget(Paragraph::new(self.status.as_str()).block(Block::bordered()), area);
+            }
+        }
+    }
+}
+
+fn level_meter(label: &str, percent: u8) -> LineGauge<'_> {
+    let color = match percent {
+        0..=60 => Color::Green,
+        61..=85 => Color::Yellow,
+        _ => Color::Red,
+    };
+    LineGauge::default()
+        .label(format!("  {} ", label))
+        .ratio(percent as f64 / 100.0)
+        .filled_style(Style::new().fg(color))
+        .unfilled_style(Style::new().fg(Color::DarkGray))
+}
+
+fn draw_history(frame: &mut Frame, area: Rect, book: &ContactBook) {
+    let items: Vec<ListItem> = CallManager::call_history()
+        .iter()
+        .rev()
+        .take(HISTORY_LEN)
+        .map(|record| {
+            let when = DateTime::<Local>::from(record.started_at).format("%H:%M");
+            let who = record
+                .remote
+                .map(|node_id| book.display_name(node_id))
+                .unwrap_or_else(|| "unknown caller".to_string());
+            ListItem::new(format!("{}  {}  {}", when, who, format_duration(record.duration)))
+        })
+        .collect();
+    frame.render_widget(List::new(items).block(Block::bordered().title(" History ")), area);
+}
+
+fn draw_incoming(frame: &mut Frame, call: &CallView) {
+    let area = frame.area();
+    let popup = Rect {
+        x: area.width.saturating_sub(44) / 2,
+        y: area.height.saturating_sub(6) / 2,
+        width: area.width.min(44),
+        height: area.height.min(6),
+    };
+    let waiting = if call.phase == CallPhase::Waiting { " (call waiting)" } else { "" };
+    let text = vec![
+        Line::from(Span::styled(call.name(), Style::new().add_modifier(Modifier::BOLD))),
+        Line::from(format!("is calling you{}", waiting)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[a]", Style::new().fg(Color::Green)),
+            Span::raw(" answer   "),
+            Span::styled("[n]", Style::new().fg(Color::Red)),
+            Span::raw(" decline"),
+        ]),
+    ];
+    frame.render_widget(Clear, popup);
+    frame.render_widget(Paragraph::new(text).centered().block(Block::bordered().title(" 📞 Incoming call ")), popup);
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// Put the terminal back the way we found it, even if the TUI bails out
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(std::io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+/// Runs the full-screen phone UI on `endpoint` until the user quits.
+///
+/// Everything it shows comes from [`CallEvent`]s and [`CallManager`], and
+/// everything it does goes through [`CallCommand`]s, like any other program
+/// built on radyo.
+pub async fn run_tui(endpoint: Endpoint, ticket: NodeTicket) -> Result<()> {
+    let (dial_tx, mut dial_rx) = mpsc::unbounded_channel();
+    let book = ContactBook::load()?;
+    let mut selected = ListState::default();
+    if !book.contacts().is_empty() {
+        selected.select(Some(0));
+    }
+    let mut app = App {
+        endpoint,
+        ticket,
+        book,
+        presence: HashMap::new(),
+        selected,
+        calls: Vec::new(),
+        input: Input::Normal,
+        status: format!("Waiting for calls - the call log is in {}", log_path()?.display()),
+        dial_tx,
+        quit: false,
+    };
+    let mut events = subscribe_events();
+    let mut keys = spawn_key_reader();
+    let mut presence = spawn_presence_checker(app.endpoint.clone(), app.book.contacts().to_vec());
+
+    terminal::enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    let mut tick = tokio::time::interval(TICK);
+
+    while !app.quit {
+        app.sync_calls();
+        terminal.draw(|frame| app.draw(frame))?;
+        tokio::select! {
+            key = keys.recv() => match key {
+                Some(key) => app.on_key(key),
+                None => break,
+            },
+            event = events.recv() => match event {
+                Ok((call_id, event)) => app.on_call_event(call_id, event),
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Some(status) = dial_rx.recv() => app.status = status,
+            Some((node_id, state)) = presence.recv() => {
+                app.presence.insert(node_id, state);
+            }
+            _ = tick.tick() => {}
+        }
+    }
+    Ok(())
+}
+
+// Check every contact's presence now and then, in the background
+fn spawn_presence_checker(endpoint: Endpoint, contacts: Vec<Contact>) -> mpsc::UnboundedReceiver<(NodeId, Presence)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut refresh = tokio::time::interval(PRESENCE_REFRESH);
+        loop {
+            refresh.tick().await;
+            for contact in &contacts {
+                let (endpoint, tx, contact) = (endpoint.clone(), tx.clone(), contact.clone());
+                tokio::spawn(async move {
+                    if let Ok(state) = probe(&endpoint, contact.ticket.node_addr().clone()).await {
+                        let _ = tx.send((contact.node_id(), state));
+                    }
+                });
+            }
+            if tx.is_closed() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+// Read keys on a plain thread, like the line-based key readers
+fn spawn_key_reader() -> mpsc::UnboundedReceiver<KeyEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) => {
+                if tx.send(key).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    });
+    rx
+}
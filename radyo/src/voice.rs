@@ -0,0 +1,489 @@
+use anyhow::Result;
+use bytes::Bytes;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use iroh::endpoint::Connection;
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn, Instrument, Span};
+
+use crate::call::{call_span, emit_event, CallEvent, CallManager};
+use crate::dtmf;
+use crate::recording::CallRecorder;
+
+// Voice travels as 16 kHz mono PCM in 20 ms frames, one QUIC datagram each
+pub const VOICE_SAMPLE_RATE: u32 = 16_000;
+pub const FRAME_SAMPLES: usize = 320;
+const FRAME_INTERVAL: Duration = Duration::from_millis(20);
+
+// Playback buffer: one second of audio, trimmed back to ~200 ms if it backs up
+const PLAYBACK_BUFFER: usize = VOICE_SAMPLE_RATE as usize;
+const MAX_PLAYBACK_DELAY: usize = VOICE_SAMPLE_RATE as usize / 5;
+
+// How often audio levels are reported while a call has voice
+const LEVEL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub const DEFAULT_VOLUME: u8 = 100;
+pub const MAX_VOLUME: u8 = 200;
+
+/// Mute, hold, volume and recording switches shared between a call and its audio threads.
+#[derive(Debug, Clone)]
+pub struct VoiceControls {
+    muted: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    volume: Arc<AtomicU8>,
+    recorder: Arc<Mutex<Option<CallRecorder>>>,
+    // Peak levels in percent since they were last read
+    mic_level: Arc<AtomicU8>,
+    speaker_level: Arc<AtomicU8>,
+}
+
+impl Default for VoiceControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VoiceControls {
+    pub fn new() -> Self {
+        Self {
+            muted: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            volume: Arc::new(AtomicU8::new(DEFAULT_VOLUME)),
+            recorder: Arc::new(Mutex::new(None)),
+            mic_level: Arc::new(AtomicU8::new(0)),
+            speaker_level: Arc::new(AtomicU8::new(0)),
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    // Paused while the call is on hold - nothing is sent or played
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Playback volume in percent, from 0 to [`MAX_VOLUME`].
+    pub fn volume(&self) -> u8 {
+        self.volume.load(Ordering::Relaxed)
+    }
+
+    pub fn set_volume(&self, percent: u8) -> u8 {
+        let percent = percent.min(MAX_VOLUME);
+        self.volume.store(percent, Ordering::Relaxed);
+        percent
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.lock().unwrap().is_some()
+    }
+
+    pub fn start_recording(&self, recorder: CallRecorder) {
+        *self.recorder.lock().unwrap() = Some(recorder);
+    }
+
+    /// Closes the recording, if there is one, and returns where it was saved.
+    pub fn stop_recording(&self) -> Option<Result<PathBuf>> {
+        self.recorder.lock().unwrap().take().map(CallRecorder::finish)
+    }
+
+    /// Peak microphone and speaker levels in percent since the last call, then resets them.
+    pub fn take_levels(&self) -> (u8, u8) {
+        (self.mic_level.swap(0, Ordering::Relaxed), self.speaker_level.swap(0, Ordering::Relaxed))
+    }
+
+    fn note_level(&self, local: bool, samples: &[i16]) {
+        let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+        let percent = (peak as u32 * 100 / i16::MAX as u32).min(100) as u8;
+        let level = if local { &self.mic_level } else { &self.speaker_level };
+        level.fetch_max(percent, Ordering::Relaxed);
+    }
+
+    // What we said and what we heard, as it goes over the network
+    fn record(&self, local: bool, samples: &[i16]) {
+        let mut recorder = self.recorder.lock().unwrap();
+        if let Some(rec) = recorder.as_mut() {
+            let result = if local { rec.record_local(samples) } else { rec.record_remote(samples) };
+            if let Err(e) = result {
+                warn!("Recording stopped: {}", e);
+                recorder.take();
+            }
+        }
+    }
+}
+
+pub fn encode_frame(seq: u32, samples: &[i16]) -> Bytes {
+    let mut data = Vec::with_capacity(4 + samples.len() * 2);
+    data.extend_from_slice(&seq.to_le_bytes());
+    for sample in samples {
+        data.extend_from_slice(&sample.to_le_bytes());
+    }
+    Bytes::from(data)
+}
+
+pub fn decode_frame(data: &[u8]) -> Option<(u32, Vec<i16>)> {
+    if data.len() < 4 || !data.len().is_multiple_of(2) {
+        return None;
+    }
+    let seq = u32::from_le_bytes(data[..4].try_into().ok()?);
+    let samples = data[4..]
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Some((seq, samples))
+}
+
+/// Linear-interpolating sample rate converter for a mono stream.
+pub struct Resampler {
+    step: f64,
+    pos: f64,
+    prev: f32,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate as f64,
+            pos: 0.0,
+            prev: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        for &sample in input {
+            while self.pos <= 1.0 {
+                out.push(self.prev + (sample - self.prev) * self.pos as f32);
+                self.pos += self.step;
+            }
+            self.pos -= 1.0;
+            self.prev = sample;
+        }
+    }
+}
+
+/// The local microphone and speaker, without the network side.
+///
+/// Each direction runs on its own thread like [`crate::audio::AudioManager`],
+/// because cpal streams can't be moved between threads. A missing device only
+/// disables that direction, the call itself carries on.
+pub struct LocalAudio {
+    stop_flag: Arc<AtomicBool>,
+}
+
+impl LocalAudio {
+    // Returns the captured microphone frames and the queue feeding the speaker
+    pub fn start(controls: VoiceControls, call_id: u128) -> (Self, tokio::sync::mpsc::Receiver<Vec<i16>>, HeapProd<f32>) {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (frame_tx, frame_rx) = tokio::sync::mpsc::channel::<Vec<i16>>(32);
+        let (producer, consumer) = HeapRb::<f32>::new(PLAYBACK_BUFFER).split();
+
+        spawn_capture(frame_tx, stop_flag.clone(), call_id);
+        spawn_playback(consumer, controls, stop_flag.clone(), call_id);
+        (Self { stop_flag }, frame_rx, producer)
+    }
+
+    // Just the speaker, for listening without a microphone
+    pub fn speaker(controls: VoiceControls, call_id: u128) -> (Self, HeapProd<f32>) {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (producer, consumer) = HeapRb::<f32>::new(PLAYBACK_BUFFER).split();
+        spawn_playback(consumer, controls, stop_flag.clone(), call_id);
+        (Self { stop_flag }, producer)
+    }
+
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for LocalAudio {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Queue voice for the speaker, dropping it when we've fallen too far behind
+pub fn queue_playback(producer: &mut HeapProd<f32>, samples: &[i16]) {
+    if producer.occupied_len() > MAX_PLAYBACK_DELAY {
+        return;
+    }
+    let samples: Vec<f32> = samples.iter().map(|s| s.to_sample::<f32>()).collect();
+    producer.push_slice(&samples);
+}
+
+/// Two-way voice for one call: microphone to datagrams and datagrams to speaker.
+pub struct VoiceLink {
+    audio: LocalAudio,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+    // DTMF digits for the sender to play in place of the mic
+    tones: mpsc::UnboundedSender<String>,
+}
+
+impl VoiceLink {
+    pub fn start(conn: Connection, controls: VoiceControls, call_id: u128) -> Self {
+        let (audio, mut frame_rx, producer) = LocalAudio::start(controls.clone(), call_id);
+
+        let send_conn = conn.clone();
+        let send_controls = controls.clone();
+        let (tones, mut tone_rx) = mpsc::unbounded_channel::<String>();
+        let sender = tokio::spawn(async move {
+            let mut seq: u32 = 0;
+            // Tones keep the mic's seq and pace; the mic is cut while they play
+            let mut tone_frames: VecDeque<Vec<i16>> = VecDeque::new();
+            let mut tick = tokio::time::interval(FRAME_INTERVAL);
+            tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut has_mic = true;
+            loop {
+                let frame = tokio::select! {
+                    frame = frame_rx.recv(), if has_mic => match frame {
+                        Some(_) if !tone_frames.is_empty() => continue,
+                        Some(_) if send_controls.is_muted() || send_controls.is_paused() => continue,
+                        Some(frame) => frame,
+                        // No microphone, but tones can still go out
+                        None => {
+                            has_mic = false;
+                            continue;
+                        }
+                    },
+                    Some(digits) = tone_rx.recv() => {
+                        tone_frames.extend(dtmf::tone_frames(&digits));
+                        continue;
+                    }
+                    _ = tick.tick(), if !tone_frames.is_empty() => {
+                        tone_frames.pop_front().expect("checked above")
+                    }
+                };
+                send_controls.note_level(true, &frame);
+                send_controls.record(true, &frame);
+                if send_conn.send_datagram(encode_frame(seq, &frame)).is_err() {
+                    break;
+                }
+                seq = seq.wrapping_add(1);
+            }
+        }.in_current_span());
+
+        let receiver = tokio::spawn(receive_voice(conn, producer, controls.clone()).in_current_span());
+        let meter = tokio::spawn(report_levels(controls, call_id));
+
+        debug!("Voice link started");
+        Self {
+            audio,
+            tasks: vec![sender, receiver, meter],
+            tones,
+        }
+    }
+
+    /// Plays DTMF digits to the other side in-band, in place of the mic.
+    pub fn send_tones(&self, digits: &str) {
+        let _ = self.tones.send(digits.to_string());
+    }
+
+    pub fn stop(&self) {
+        self.audio.stop();
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for VoiceLink {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn receive_voice(conn: Connection, mut producer: HeapProd<f32>, controls: VoiceControls) {
+    while let Ok(datagram) = conn.read_datagram().await {
+        let Some((_seq, samples)) = decode_frame(&datagram) else {
+            continue;
+        };
+        // Drop audio while on hold
+        if controls.is_paused() {
+            continue;
+        }
+        controls.note_level(false, &samples);
+        controls.record(false, &samples);
+        queue_playback(&mut producer, &samples);
+    }
+}
+
+// Report the audio levels for meters, as CallEvent::Levels
+async fn report_levels(controls: VoiceControls, call_id: u128) {
+    let mut interval = tokio::time::interval(LEVEL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let (mic, speaker) = controls.take_levels();
+        emit_event(call_id, CallEvent::Levels { mic, speaker });
+    }
+}
+
+// The microphone from radyo.toml's audio.input_device, or the default one
+fn input_device() -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    match CallManager::audio_settings().input_device {
+        Some(name) => host
+            .input_devices()?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| anyhow::anyhow!("no microphone named {}", name)),
+        None => host.default_input_device().ok_or_else(|| anyhow::anyhow!("no microphone found")),
+    }
+}
+
+// The speaker from radyo.toml's audio.output_device, or the default one
+fn output_device() -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    match CallManager::audio_settings().output_device {
+        Some(name) => host
+            .output_devices()?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| anyhow::anyhow!("no speaker named {}", name)),
+        None => host.default_output_device().ok_or_else(|| anyhow::anyhow!("no speaker found")),
+    }
+}
+
+fn spawn_capture(frame_tx: tokio::sync::mpsc::Sender<Vec<i16>>, stop_flag: Arc<AtomicBool>, call_id: u128) {
+    std::thread::spawn(move || {
+        let _span = call_span(call_id).entered();
+        let capture_result = (|| -> Result<()> {
+            let device = input_device()?;
+            let config = device.default_input_config()?;
+            let channels = config.channels() as usize;
+            let mut resampler = Resampler::new(config.sample_rate().0, VOICE_SAMPLE_RATE);
+            let mut pending: Vec<f32> = Vec::with_capacity(FRAME_SAMPLES * 2);
+
+            // Downmix to mono, resample, and hand off complete frames
+            let mut on_input = move |mono: &[f32]| {
+                resampler.process(mono, &mut pending);
+                while pending.len() >= FRAME_SAMPLES {
+                    let frame: Vec<i16> = pending.drain(..FRAME_SAMPLES).map(|s| s.to_sample::<i16>()).collect();
+                    let _ = frame_tx.try_send(frame);
+                }
+            };
+            let stream = match config.sample_format() {
+                cpal::SampleFormat::F32 => build_input::<f32>(&device, &config.into(), channels, move |m| on_input(m))?,
+                cpal::SampleFormat::I16 => build_input::<i16>(&device, &config.into(), channels, move |m| on_input(m))?,
+                cpal::SampleFormat::U16 => build_input::<u16>(&device, &config.into(), channels, move |m| on_input(m))?,
+                format => anyhow::bail!("unsupported microphone sample format {}", format),
+            };
+            stream.play()?;
+
+            while !stop_flag.load(Ordering::Relaxed) {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = capture_result {
+            warn!("Microphone unavailable - the other side won't hear you: {}", e);
+        }
+    });
+}
+
+fn build_input<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    mut on_mono: impl FnMut(&[f32]) + Send + 'static,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut mono = Vec::new();
+    let span = Span::current();
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            mono.clear();
+            mono.extend(data.chunks(channels).map(|frame| {
+                frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / channels as f32
+            }));
+            on_mono(&mono);
+        },
+        move |e| span.in_scope(|| warn!("Microphone stream error: {}", e)),
+        None,
+    )?;
+    Ok(stream)
+}
+
+fn spawn_playback(consumer: HeapCons<f32>, controls: VoiceControls, stop_flag: Arc<AtomicBool>, call_id: u128) {
+    std::thread::spawn(move || {
+        let _span = call_span(call_id).entered();
+        let playback_result = (|| -> Result<()> {
+            let device = output_device()?;
+            let config = device.default_output_config()?;
+            let channels = config.channels() as usize;
+            let output_rate = config.sample_rate().0;
+            let stream = match config.sample_format() {
+                cpal::SampleFormat::F32 => build_output::<f32>(&device, &config.into(), channels, output_rate, consumer, controls)?,
+                cpal::SampleFormat::I16 => build_output::<i16>(&device, &config.into(), channels, output_rate, consumer, controls)?,
+                cpal::SampleFormat::U16 => build_output::<u16>(&device, &config.into(), channels, output_rate, consumer, controls)?,
+                format => anyhow::bail!("unsupported speaker sample format {}", format),
+            };
+            stream.play()?;
+
+            while !stop_flag.load(Ordering::Relaxed) {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = playback_result {
+            warn!("Speaker unavailable - you won't hear the other side: {}", e);
+        }
+    });
+}
+
+fn build_output<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    output_rate: u32,
+    mut consumer: HeapCons<f32>,
+    controls: VoiceControls,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    // Pull-side linear interpolation from VOICE_SAMPLE_RATE to the device rate
+    let step = VOICE_SAMPLE_RATE as f64 / output_rate as f64;
+    let (mut pos, mut prev, mut next) = (0.0f64, 0.0f32, 0.0f32);
+    let span = Span::current();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            let gain = controls.volume() as f32 / 100.0;
+            for frame in data.chunks_mut(channels) {
+                while pos >= 1.0 {
+                    prev = next;
+                    next = consumer.try_pop().unwrap_or(0.0);
+                    pos -= 1.0;
+                }
+                let sample = (prev + (next - prev) * pos as f32) * gain;
+                pos += step;
+                for out in frame.iter_mut() {
+                    *out = T::from_sample(sample.clamp(-1.0, 1.0));
+                }
+            }
+        },
+        move |e| span.in_scope(|| warn!("Speaker stream error: {}", e)),
+        None,
+    )?;
+    Ok(stream)
+}
@@ -2,7 +2,6 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use iroh::{
-    Endpoint,
     NodeAddr,
     NodeId,
 };
@@ -13,7 +12,9 @@ use iroh::protocol::{
     Router
 };
 use n0_future::boxed::BoxFuture;
+use network::NetworkArgs;
 use std::fmt::Debug;
+#[allow(unused_imports)]
 use tokio::io::{copy, AsyncWriteExt};
 
 #[derive(Subcommand)]
@@ -31,12 +32,15 @@ enum Cmd {
 struct Cli {
     #[command(subcommand)]
     cmd: Cmd,
+    #[command(flatten)]
+    network: NetworkArgs,
 }
 
 #[derive(Debug, Clone)]
 struct Echo;
 
 impl ProtocolHandler for Echo {
+    #[allow(refining_impl_trait)]
     fn accept(&self, connection: Connection) -> BoxFuture<Result<(), AcceptError>> {
         Box::pin(async move {
                     let node_id = connection.remote_node_id()?;
@@ -70,7 +74,7 @@ async fn main() -> Result<()> {
 
     match cli.cmd {
         Cmd::Listen => {
-            let router = start_accept_side().await?;
+            let router = start_accept_side(&cli.network).await?;
             let node_id = router.endpoint().node_id();
             println!("Echo server started!");
             println!("Node ID: {}", format!("{}", node_id).blue());
@@ -84,23 +88,23 @@ async fn main() -> Result<()> {
         Cmd::Connect { node_id } => {
             let node_id: NodeId = node_id.parse().map_err(|_| anyhow::anyhow!("Invalid node ID format"))?;
             let node_addr = NodeAddr::from(node_id);
-            connect_side(node_addr).await?;
+            connect_side(node_addr, &cli.network).await?;
         }
     }
     Ok(())
 }
 
-async fn start_accept_side() -> Result<Router> {
-    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
+async fn start_accept_side(network: &NetworkArgs) -> Result<Router> {
+    let endpoint = network.endpoint_builder()?.bind().await?;
     // Build our protocol handler and add our protocol, identified by its ALPN, and spawn the node.
     let router = Router::builder(endpoint).accept(ALPN, Echo).spawn();
     Ok(router)
 }
 
 
-async fn connect_side(node_addr: NodeAddr) -> Result<()> {
+async fn connect_side(node_addr: NodeAddr, network: &NetworkArgs) -> Result<()> {
     println!("Connecting to: {}", format!("{:?}", node_addr).blue());
-    let endpoint = Endpoint::builder().discovery_n0().bind().await?;
+    let endpoint = network.endpoint_builder()?.bind().await?;
     let conn = endpoint.connect(node_addr, ALPN).await?;
     println!("Connected! Sending message...");
 